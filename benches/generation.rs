@@ -0,0 +1,36 @@
+/*!
+ * Times world generation for every world size the game offers, on one
+ * thread and with the noise stages spread over every core.
+ * Run with: cargo bench --bench generation
+ * Pass a number to change how many seeds are generated per size, at least 1
+ */
+
+use mc_classic_js::ClassicGenerator;
+
+use std::time::{Duration, Instant};
+
+fn main () {
+    //The mean divides by the run count, so anything below 1 runs once
+    let runs: u32 = std::env::args().skip(1).find_map(|arg| arg.parse::<i64>().ok()).unwrap_or(5).clamp(1, u32::MAX as i64) as u32;
+    let cores: usize = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+    let thread_counts: Vec<usize> = if cores > 1 {vec![1, cores]} else {vec![1]};
+
+    for world_size in [128, 256, 512] {
+        for &threads in &thread_counts {
+            let mut times: Vec<Duration> = Vec::new();
+
+            for seed in 0..runs as i64 {
+                let start: Instant = Instant::now();
+                let tile_map = ClassicGenerator::new(seed).size(world_size).threads(threads).generate().unwrap();
+                times.push(start.elapsed());
+
+                std::hint::black_box(tile_map);
+            }
+
+            let total: Duration = times.iter().sum();
+            let fastest: Duration = times.iter().min().copied().unwrap_or_default();
+
+            println!("{:>3}x{:<3} {:>2} threads  mean {:>10.2?}  min {:>10.2?}  ({} seeds)", world_size, world_size, threads, total / runs, fastest, runs);
+        }
+    }
+}
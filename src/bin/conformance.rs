@@ -0,0 +1,67 @@
+/*!
+ * Compares tile maps dumped from the js game against this crate's generator.
+ *
+ * conformance compare <world size> <seed> <dump>
+ *     prints a ConformanceReport, exits with 1 if any tile differs
+ * conformance fixture <world size> <seed> [dump]
+ *     prints a fixture line, from the dump if one is given, otherwise a
+ *     snapshot of this crate's own output
+ *
+ * A dump is the worker's tile array, as raw bytes or as a json array of block ids.
+ */
+#![allow(clippy::needless_return)]
+
+use mc_classic_js::{read_tile_map_dump, ConformanceReport, Fixture, Result, TileMap};
+
+use std::env;
+use std::fs;
+use std::process;
+
+const USAGE: &str = "usage:\n  conformance compare <world size> <seed> <dump>\n  conformance fixture <world size> <seed> [dump]";
+
+fn main () {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    match run(&args) {
+        Ok(true) => (),
+        Ok(false) => process::exit(1),
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(2);
+        }
+    }
+}
+
+//Returns false if a comparison found mismatches
+fn run (args: &[String]) -> std::result::Result<bool, String> {
+    let command: &str = args.first().map(|s| s.as_str()).unwrap_or("");
+    if args.len() < 3 { return Err(USAGE.to_string()); }
+
+    let world_size: i32 = args[1].parse().map_err(|_| format!("invalid world size {:?}", args[1]))?;
+    let seed: i64 = args[2].parse().map_err(|_| format!("invalid seed {:?}", args[2]))?;
+    let dump: Option<&String> = args.get(3);
+
+    match (command, dump) {
+        ("compare", Some(path)) => {
+            let expected: TileMap = read_dump(path, world_size).map_err(|e| e.to_string())?;
+            let report: ConformanceReport = ConformanceReport::check(world_size, seed, &expected).map_err(|e| e.to_string())?;
+            print!("{}", report);
+            return Ok(report.is_match());
+        },
+        ("fixture", Some(path)) => {
+            let tile_map: TileMap = read_dump(path, world_size).map_err(|e| e.to_string())?;
+            println!("{}", Fixture::from_tile_map(world_size, seed, &tile_map));
+            return Ok(true);
+        },
+        ("fixture", None) => {
+            println!("{}", Fixture::generate(world_size, seed).map_err(|e| e.to_string())?);
+            return Ok(true);
+        },
+        _ => return Err(USAGE.to_string())
+    }
+}
+
+fn read_dump (path: &str, world_size: i32) -> Result<TileMap> {
+    let bytes: Vec<u8> = fs::read(path)?;
+    return read_tile_map_dump(&bytes, world_size);
+}
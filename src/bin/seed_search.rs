@@ -0,0 +1,102 @@
+/*!
+ * Searches a range of seeds for worlds that match every predicate given.
+ *
+ * seed_search <world size> <start> <end> [options]
+ *     --blocks <block> <count>    at least count tiles of a block, ie --blocks gold_ore 400
+ *     --island <max area>         spawn on an island of at most max area columns
+ *     --lake <min area>           an approximate lake of at least min area columns near the middle
+ *     --threads <n>               threads to scan with, 0 for every core (default 0)
+ *     --preview-step <n>          step of the preview the lake predicate reads (default 4)
+ *     --limit <n>                 how many of the best seeds to print (default 20)
+ *     --stop-after <n>            stop once n seeds have matched
+ *     --checkpoint <file>         save progress to file, and resume from it if it exists
+ *
+ * Prints the matches best first as "seed score".
+ */
+#![allow(clippy::needless_return)]
+
+use mc_classic_js::{block_count_at_least, lake_near_centre, spawn_on_island, Block, SearchCheckpoint, SearchOutcome, SeedSearch};
+
+use std::env;
+use std::path::Path;
+use std::process;
+
+const USAGE: &str = "usage: seed_search <world size> <start> <end> [--blocks <block> <count>] [--island <max area>] [--lake <min area>] [--threads <n>] [--preview-step <n>] [--limit <n>] [--stop-after <n>] [--checkpoint <file>]";
+
+fn main () {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    if let Err(e) = run(&args) {
+        eprintln!("{}", e);
+        process::exit(2);
+    }
+}
+
+fn run (args: &[String]) -> Result<(), String> {
+    if args.len() < 3 { return Err(USAGE.to_string()); }
+
+    let world_size: i32 = parse(&args[0], "world size")?;
+    let start: i64 = parse(&args[1], "start")?;
+    let end: i64 = parse(&args[2], "end")?;
+    let mut search: SeedSearch = SeedSearch::new(world_size, start, end).limit(20);
+    let mut checkpoint: Option<String> = None;
+
+    let mut rest = args[3..].iter();
+    let mut predicates: usize = 0;
+    while let Some(option) = rest.next() {
+        let mut value = |name: &str| rest.next().cloned().ok_or_else(|| format!("{} needs a {}", option, name));
+
+        match option.as_str() {
+            "--blocks" => {
+                let name: String = value("block")?;
+                let block: Block = Block::from_name(&name).ok_or_else(|| format!("unknown block {:?}", name))?;
+                search = search.predicate(block_count_at_least(block, parse(&value("count")?, "count")?));
+                predicates += 1;
+            },
+            "--island" => {
+                search = search.predicate(spawn_on_island(parse(&value("max area")?, "max area")?));
+                predicates += 1;
+            },
+            "--lake" => {
+                search = search.predicate(lake_near_centre(parse(&value("min area")?, "min area")?));
+                predicates += 1;
+            },
+            "--threads" => search = search.threads(parse(&value("count")?, "thread count")?),
+            "--preview-step" => search = search.preview_step(parse(&value("step")?, "step")?),
+            "--limit" => search = search.limit(parse(&value("count")?, "limit")?),
+            "--stop-after" => search = search.stop_after(parse(&value("count")?, "count")?),
+            "--checkpoint" => checkpoint = Some(value("file")?),
+            _ => return Err(USAGE.to_string())
+        }
+    }
+    if predicates == 0 { return Err("no predicates given, every seed would match".to_string()); }
+
+    if let Some(path) = &checkpoint {
+        let path: &Path = Path::new(path);
+        if path.exists() {
+            let saved: SearchCheckpoint = SearchCheckpoint::load(path).map_err(|e| e.to_string())?;
+            if saved.world_size != world_size || saved.start != start || saved.end != end {
+                return Err(format!("{} is a checkpoint of a different search", path.display()));
+            }
+            eprintln!("resuming from seed {}", saved.next_seed);
+            search = search.resume_from(&saved);
+        }
+        search = search.checkpoint(path);
+    }
+
+    let outcome: SearchOutcome = search.run().map_err(|e| e.to_string())?;
+    for m in &outcome.matches {
+        println!("{} {}", m.seed, m.score);
+    }
+
+    if outcome.checkpoint.is_finished() {
+        eprintln!("scanned {} seeds, {} matches", outcome.scanned, outcome.matches.len());
+    } else {
+        eprintln!("scanned {} seeds, {} matches, stopped before seed {}", outcome.scanned, outcome.matches.len(), outcome.checkpoint.next_seed);
+    }
+    return Ok(());
+}
+
+fn parse<T: std::str::FromStr> (text: &str, name: &str) -> Result<T, String> {
+    return text.parse().map_err(|_| format!("invalid {} {:?}", name, text));
+}
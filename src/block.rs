@@ -0,0 +1,325 @@
+use crate::error::Error;
+
+use serde::de::{self, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use std::fmt;
+
+/**
+ * Block enum stores every block in the classic js palette,
+ * where the discriminant is the id the game uses inside tile
+ * maps and the bt value of changedBlocks. The ids the world
+ * generator places are:
+ * 1 grass, 2 rock, 3 dirt, 7 water, 11 sand, 12 gravel,
+ * 13 tree trunk, 14 leaves, 17 lava, 18 gold ore, 19 iron ore, 20 coal ore
+ * Classic js only has a single water and lava tile, there is
+ * no calm variant like in the java versions
+ */
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Block {
+    Air = 0,
+    Grass = 1,
+    Stone = 2,
+    Dirt = 3,
+    Cobblestone = 4,
+    Planks = 5,
+    Sapling = 6,
+    Water = 7,
+    Bedrock = 8,
+    Glass = 9,
+    Sponge = 10,
+    Sand = 11,
+    Gravel = 12,
+    Log = 13,
+    Leaves = 14,
+    Dandelion = 15,
+    Rose = 16,
+    Lava = 17,
+    GoldOre = 18,
+    IronOre = 19,
+    CoalOre = 20,
+    BrownMushroom = 21,
+    RedMushroom = 22,
+    Bricks = 23,
+    MossyCobblestone = 24,
+    Bookshelf = 25,
+    Tnt = 26,
+    Obsidian = 27,
+    Slab = 28,
+    RedWool = 29,
+    OrangeWool = 30,
+    YellowWool = 31,
+    LimeWool = 32,
+    GreenWool = 33,
+    TealWool = 34,
+    AquaWool = 35,
+    CyanWool = 36,
+    BlueWool = 37,
+    IndigoWool = 38,
+    VioletWool = 39,
+    MagentaWool = 40,
+    PinkWool = 41,
+    BlackWool = 42,
+    GrayWool = 43,
+    WhiteWool = 44,
+    GoldBlock = 45,
+    IronBlock = 46
+}
+
+impl Block {
+
+    //Every block in id order, ALL[id] is the block with that id
+    pub const ALL: [Block; 47] = [
+        Block::Air, Block::Grass, Block::Stone, Block::Dirt, Block::Cobblestone, Block::Planks,
+        Block::Sapling, Block::Water, Block::Bedrock, Block::Glass, Block::Sponge, Block::Sand,
+        Block::Gravel, Block::Log, Block::Leaves, Block::Dandelion, Block::Rose, Block::Lava,
+        Block::GoldOre, Block::IronOre, Block::CoalOre, Block::BrownMushroom, Block::RedMushroom,
+        Block::Bricks, Block::MossyCobblestone, Block::Bookshelf, Block::Tnt, Block::Obsidian,
+        Block::Slab, Block::RedWool, Block::OrangeWool, Block::YellowWool, Block::LimeWool,
+        Block::GreenWool, Block::TealWool, Block::AquaWool, Block::CyanWool, Block::BlueWool,
+        Block::IndigoWool, Block::VioletWool, Block::MagentaWool, Block::PinkWool, Block::BlackWool,
+        Block::GrayWool, Block::WhiteWool, Block::GoldBlock, Block::IronBlock
+    ];
+
+    /**
+     * Returns the id of the block as stored in tile maps and saves
+     */
+    pub const fn id (self) -> u8 {
+        return self as u8;
+    }
+
+    /**
+     * Returns the block for a given id, or None if the
+     * id is not part of the classic js palette
+     */
+    pub fn from_id (id: u8) -> Option<Block> {
+        return Block::ALL.get(id as usize).copied();
+    }
+
+    /**
+     * Returns the snake case name of the block, ie "coal_ore"
+     */
+    pub const fn name (self) -> &'static str {
+        match self {
+            Block::Air => "air",
+            Block::Grass => "grass",
+            Block::Stone => "stone",
+            Block::Dirt => "dirt",
+            Block::Cobblestone => "cobblestone",
+            Block::Planks => "planks",
+            Block::Sapling => "sapling",
+            Block::Water => "water",
+            Block::Bedrock => "bedrock",
+            Block::Glass => "glass",
+            Block::Sponge => "sponge",
+            Block::Sand => "sand",
+            Block::Gravel => "gravel",
+            Block::Log => "log",
+            Block::Leaves => "leaves",
+            Block::Dandelion => "dandelion",
+            Block::Rose => "rose",
+            Block::Lava => "lava",
+            Block::GoldOre => "gold_ore",
+            Block::IronOre => "iron_ore",
+            Block::CoalOre => "coal_ore",
+            Block::BrownMushroom => "brown_mushroom",
+            Block::RedMushroom => "red_mushroom",
+            Block::Bricks => "bricks",
+            Block::MossyCobblestone => "mossy_cobblestone",
+            Block::Bookshelf => "bookshelf",
+            Block::Tnt => "tnt",
+            Block::Obsidian => "obsidian",
+            Block::Slab => "slab",
+            Block::RedWool => "red_wool",
+            Block::OrangeWool => "orange_wool",
+            Block::YellowWool => "yellow_wool",
+            Block::LimeWool => "lime_wool",
+            Block::GreenWool => "green_wool",
+            Block::TealWool => "teal_wool",
+            Block::AquaWool => "aqua_wool",
+            Block::CyanWool => "cyan_wool",
+            Block::BlueWool => "blue_wool",
+            Block::IndigoWool => "indigo_wool",
+            Block::VioletWool => "violet_wool",
+            Block::MagentaWool => "magenta_wool",
+            Block::PinkWool => "pink_wool",
+            Block::BlackWool => "black_wool",
+            Block::GrayWool => "gray_wool",
+            Block::WhiteWool => "white_wool",
+            Block::GoldBlock => "gold_block",
+            Block::IronBlock => "iron_block"
+        }
+    }
+
+    /**
+     * Returns the block for a given name. Names are case insensitive
+     * and ignore underscores and spaces, so "Coal Ore", "coal_ore" and
+     * "coalOre" all match. The tile names used by the js source
+     * (rock, treeTrunk, calmWater etc.) are accepted as aliases
+     */
+    pub fn from_name (name: &str) -> Option<Block> {
+        let normalized: String = name.chars()
+            .filter(|c| *c != '_' && *c != ' ')
+            .map(|c| c.to_ascii_lowercase())
+            .collect();
+
+        //Aliases from the js tile names
+        let alias: Option<Block> = match normalized.as_str() {
+            "empty" => Some(Block::Air),
+            "rock" => Some(Block::Stone),
+            "stonebrick" => Some(Block::Cobblestone),
+            "wood" => Some(Block::Planks),
+            "bush" => Some(Block::Sapling),
+            "calmwater" => Some(Block::Water),
+            "unbreakable" => Some(Block::Bedrock),
+            "treetrunk" => Some(Block::Log),
+            "calmlava" => Some(Block::Lava),
+            _ => None
+        };
+        if alias.is_some() { return alias; }
+
+        return Block::ALL.iter().copied().find(|b| b.name().replace('_', "") == normalized);
+    }
+
+    /**
+     * True if entities collide with the block
+     */
+    pub const fn is_solid (self) -> bool {
+        return !matches!(self,
+            Block::Air | Block::Water | Block::Lava | Block::Sapling |
+            Block::Dandelion | Block::Rose | Block::BrownMushroom | Block::RedMushroom
+        );
+    }
+
+    /**
+     * True for water and lava
+     */
+    pub const fn is_liquid (self) -> bool {
+        return matches!(self, Block::Water | Block::Lava);
+    }
+
+    /**
+     * True if the block can be seen through, so the faces
+     * of neighbouring blocks are still rendered
+     */
+    pub const fn is_transparent (self) -> bool {
+        return matches!(self,
+            Block::Air | Block::Water | Block::Glass | Block::Leaves | Block::Sapling |
+            Block::Dandelion | Block::Rose | Block::BrownMushroom | Block::RedMushroom
+        );
+    }
+
+    /**
+     * True if the block casts a shadow on the blocks below it in the classic
+     * renderer. Glass and plants let light through, everything else including
+     * leaves and liquids blocks it
+     */
+    pub const fn blocks_light (self) -> bool {
+        return !matches!(self,
+            Block::Air | Block::Glass | Block::Sapling |
+            Block::Dandelion | Block::Rose | Block::BrownMushroom | Block::RedMushroom
+        );
+    }
+
+    /**
+     * True if the block falls when there is nothing below it
+     */
+    pub const fn is_gravity_affected (self) -> bool {
+        return matches!(self, Block::Sand | Block::Gravel);
+    }
+}
+
+impl From<Block> for u8 {
+    fn from (block: Block) -> u8 {
+        return block.id();
+    }
+}
+
+impl TryFrom<u8> for Block {
+    type Error = Error;
+
+    fn try_from (id: u8) -> Result<Block, Error> {
+        return Block::from_id(id).ok_or(Error::UnknownBlock(id));
+    }
+}
+
+impl fmt::Display for Block {
+    fn fmt (&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return f.write_str(self.name());
+    }
+}
+
+//Blocks are written as their id, the same as bt in the savedGame
+impl Serialize for Block {
+    fn serialize<S: Serializer> (&self, serializer: S) -> Result<S::Ok, S::Error> {
+        return serializer.serialize_u8(self.id());
+    }
+}
+
+impl<'de> Deserialize<'de> for Block {
+    fn deserialize<D: Deserializer<'de>> (deserializer: D) -> Result<Self, D::Error> {
+        return deserializer.deserialize_u8(BlockVisitor);
+    }
+}
+
+struct BlockVisitor;
+
+impl Visitor<'_> for BlockVisitor {
+    type Value = Block;
+
+    fn expecting (&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return f.write_str("a block id from 0 to 46");
+    }
+
+    fn visit_u64<E: de::Error> (self, id: u64) -> Result<Block, E> {
+        return u8::try_from(id).ok().and_then(Block::from_id).ok_or_else(|| E::invalid_value(de::Unexpected::Unsigned(id), &self));
+    }
+
+    fn visit_i64<E: de::Error> (self, id: i64) -> Result<Block, E> {
+        return u8::try_from(id).ok().and_then(Block::from_id).ok_or_else(|| E::invalid_value(de::Unexpected::Signed(id), &self));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ids_round_trip_over_the_palette () {
+        for (id, block) in Block::ALL.iter().enumerate() {
+            assert_eq!(block.id() as usize, id);
+            assert_eq!(Block::from_id(block.id()), Some(*block));
+            assert_eq!(Block::from_name(block.name()), Some(*block));
+        }
+        assert_eq!(Block::from_id(47), None);
+    }
+
+    #[test]
+    fn names_accept_the_js_aliases () {
+        assert_eq!(Block::from_name("rock"), Some(Block::Stone));
+        assert_eq!(Block::from_name("treeTrunk"), Some(Block::Log));
+        assert_eq!(Block::from_name("calmWater"), Some(Block::Water));
+        assert_eq!(Block::from_name("calm_lava"), Some(Block::Lava));
+        assert_eq!(Block::from_name("empty"), Some(Block::Air));
+        assert_eq!(Block::from_name("Coal Ore"), Some(Block::CoalOre));
+        assert_eq!(Block::from_name("coalOre"), Some(Block::CoalOre));
+        assert_eq!(Block::from_name("diamond_ore"), None);
+    }
+
+    #[test]
+    fn unknown_ids_fail_to_convert () {
+        assert_eq!(Block::try_from(20).unwrap(), Block::CoalOre);
+        assert!(matches!(Block::try_from(47), Err(Error::UnknownBlock(47))));
+        assert!(matches!(Block::try_from(255), Err(Error::UnknownBlock(255))));
+    }
+
+    #[test]
+    fn serializes_as_the_block_id () {
+        assert_eq!(serde_json::to_string(&Block::GoldOre).unwrap(), "18");
+        assert_eq!(serde_json::from_str::<Block>("18").unwrap(), Block::GoldOre);
+        assert!(serde_json::from_str::<Block>("47").is_err());
+        assert!(serde_json::from_str::<Block>("-1").is_err());
+        assert!(serde_json::from_str::<Block>("\"gold_ore\"").is_err());
+    }
+}
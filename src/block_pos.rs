@@ -0,0 +1,137 @@
+use crate::error::Error;
+
+use serde::de::{self, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+/**
+ * BlockPos struct stores the position of a block in the world,
+ * it is used as the key of changedBlocks where it is written as
+ * p{x}_{y}_{z}, ie p12_40_7 is X:12, Y:40, Z:7
+ *
+ * Positions are ordered the same way a tile map is laid out,
+ * by Y, then Z, then X
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct BlockPos {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32
+}
+
+impl BlockPos {
+    pub fn new (x: i32, y: i32, z: i32) -> Self {
+        BlockPos { x, y, z }
+    }
+}
+
+impl fmt::Display for BlockPos {
+    fn fmt (&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "p{}_{}_{}", self.x, self.y, self.z);
+    }
+}
+
+/**
+ * Parses a key in the p{x}_{y}_{z} format. Only the exact format
+ * the game writes is accepted, so every parsed key formats back
+ * into the same string
+ */
+impl FromStr for BlockPos {
+    type Err = Error;
+
+    fn from_str (s: &str) -> Result<Self, Error> {
+        let invalid = || Error::InvalidBlockPos(s.to_string());
+
+        let coords: Vec<&str> = s.strip_prefix('p').ok_or_else(invalid)?.split('_').collect();
+        if coords.len() != 3 { return Err(invalid()); }
+
+        let x: i32 = coords[0].parse().map_err(|_| invalid())?;
+        let y: i32 = coords[1].parse().map_err(|_| invalid())?;
+        let z: i32 = coords[2].parse().map_err(|_| invalid())?;
+        let pos: BlockPos = BlockPos::new(x, y, z);
+
+        //Rejecting keys such as p+1_02_3 that would not format back the same way
+        if pos.to_string() != s { return Err(invalid()); }
+
+        return Ok(pos);
+    }
+}
+
+impl Ord for BlockPos {
+    fn cmp (&self, other: &Self) -> Ordering {
+        return (self.y, self.z, self.x).cmp(&(other.y, other.z, other.x));
+    }
+}
+
+impl PartialOrd for BlockPos {
+    fn partial_cmp (&self, other: &Self) -> Option<Ordering> {
+        return Some(self.cmp(other));
+    }
+}
+
+//Block positions are written as strings so they can be used as json object keys
+impl Serialize for BlockPos {
+    fn serialize<S: Serializer> (&self, serializer: S) -> Result<S::Ok, S::Error> {
+        return serializer.collect_str(self);
+    }
+}
+
+impl<'de> Deserialize<'de> for BlockPos {
+    fn deserialize<D: Deserializer<'de>> (deserializer: D) -> Result<Self, D::Error> {
+        return deserializer.deserialize_str(BlockPosVisitor);
+    }
+}
+
+struct BlockPosVisitor;
+
+impl Visitor<'_> for BlockPosVisitor {
+    type Value = BlockPos;
+
+    fn expecting (&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return f.write_str("a block position in the format p{x}_{y}_{z}");
+    }
+
+    fn visit_str<E: de::Error> (self, s: &str) -> Result<BlockPos, E> {
+        return s.parse().map_err(|_| E::invalid_value(de::Unexpected::Str(s), &self));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_and_formats_the_same_key () {
+        for key in ["p0_0_0", "p12_40_7", "p-3_0_-1", "p511_63_511"] {
+            let pos: BlockPos = key.parse().unwrap();
+            assert_eq!(pos.to_string(), key);
+        }
+        assert_eq!("p12_40_7".parse::<BlockPos>().unwrap(), BlockPos::new(12, 40, 7));
+    }
+
+    #[test]
+    fn rejects_keys_that_would_not_round_trip () {
+        for key in ["", "p", "12_40_7", "p1_2", "p1_2_3_4", "p+1_2_3", "p01_2_3", "p1_-0_3", "p1_2_3 ", "P1_2_3", "p1_a_3", "p-_2_3"] {
+            assert!(matches!(key.parse::<BlockPos>(), Err(Error::InvalidBlockPos(_))), "{:?} was accepted", key);
+        }
+    }
+
+    #[test]
+    fn orders_by_y_then_z_then_x () {
+        let mut positions: Vec<BlockPos> = vec![BlockPos::new(0, 1, 0), BlockPos::new(1, 0, 1), BlockPos::new(0, 0, 1), BlockPos::new(5, 0, 0)];
+        positions.sort();
+        assert_eq!(positions, vec![BlockPos::new(5, 0, 0), BlockPos::new(0, 0, 1), BlockPos::new(1, 0, 1), BlockPos::new(0, 1, 0)]);
+    }
+
+    #[test]
+    fn serializes_as_a_json_string () {
+        let pos: BlockPos = BlockPos::new(4, 5, 6);
+        assert_eq!(serde_json::to_string(&pos).unwrap(), "\"p4_5_6\"");
+        assert_eq!(serde_json::from_str::<BlockPos>("\"p4_5_6\"").unwrap(), pos);
+        assert!(serde_json::from_str::<BlockPos>("\"p04_5_6\"").is_err());
+        assert!(serde_json::from_str::<BlockPos>("12").is_err());
+    }
+}
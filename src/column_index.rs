@@ -0,0 +1,202 @@
+use crate::block::Block;
+use crate::tile_map::TileMap;
+use crate::JSLevel;
+
+/**
+ * ColumnInfo struct sums up a single vertical column of a world
+ * highest: y of the highest block that is not air, -1 if the column is empty
+ * surface: y of the highest solid block, the one a player stands on, -1 if there is none
+ * liquid: the water or lava at the top of the column, None if the top is not liquid
+ * liquid_depth: how many tiles of liquid are stacked at the top of the column, 0 without liquid
+ * light_depth: y of the highest block that blocks light, the same as the classic
+ * renderer's light depth. Blocks below it are in shadow, 0 if nothing blocks light
+ */
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ColumnInfo {
+    pub highest: i32,
+    pub surface: i32,
+    pub liquid: Option<Block>,
+    pub liquid_depth: i32,
+    pub light_depth: i32
+}
+
+impl ColumnInfo {
+
+    /**
+     * Scans a column of a tile map from the top down
+     */
+    pub fn scan (tile_map: &TileMap, x: i32, z: i32) -> Self {
+        let block = |y: i32| tile_map.get(x, y, z).unwrap_or(Block::Air);
+
+        let mut highest: i32 = tile_map.height() - 1;
+        while highest >= 0 && block(highest) == Block::Air { highest -= 1; }
+
+        let mut surface: i32 = highest;
+        while surface >= 0 && !block(surface).is_solid() { surface -= 1; }
+
+        let liquid: Option<Block> = Some(block(highest)).filter(|b| highest >= 0 && b.is_liquid());
+        let mut liquid_depth: i32 = 0;
+        while liquid.is_some() && highest - liquid_depth >= 0 && block(highest - liquid_depth).is_liquid() { liquid_depth += 1; }
+
+        //Same loop as the classic calcLightDepths, which stops at 0 either way
+        let mut light_depth: i32 = tile_map.height() - 1;
+        while light_depth > 0 && !block(light_depth).blocks_light() { light_depth -= 1; }
+
+        return ColumnInfo { highest, surface, liquid, liquid_depth, light_depth };
+    }
+}
+
+/**
+ * ColumnIndex struct stores a ColumnInfo for every column of a world, built once so
+ * heights, liquid depths and shadows can be looked up without scanning the tiles.
+ * It is kept up to date through set and apply_changed_blocks, which only rescan the
+ * columns they change. If the tile map is changed some other way, call update for
+ * every column that changed
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ColumnIndex {
+    width: i32,
+    depth: i32,
+    columns: Vec<ColumnInfo>
+}
+
+impl ColumnIndex {
+
+    /**
+     * Builds the index of every column of a tile map
+     */
+    pub fn new (tile_map: &TileMap) -> Self {
+        let columns: Vec<ColumnInfo> = tile_map.columns().map(|column| ColumnInfo::scan(tile_map, column.x, column.z)).collect();
+        ColumnIndex { width: tile_map.width(), depth: tile_map.depth(), columns }
+    }
+
+    pub fn width (&self) -> i32 { self.width }
+    pub fn depth (&self) -> i32 { self.depth }
+
+    /**
+     * Returns the column at x, z, or None if it is outside of the world
+     */
+    pub fn get (&self, x: i32, z: i32) -> Option<&ColumnInfo> {
+        if x < 0 || z < 0 || x >= self.width || z >= self.depth { return None; }
+        return self.columns.get((x + z * self.width) as usize);
+    }
+
+    /**
+     * Every column, X fastest then Z, the same order as TileMap::columns
+     */
+    pub fn columns (&self) -> &[ColumnInfo] {
+        return &self.columns;
+    }
+
+    /**
+     * Returns the y of the highest solid block at x, z, None if
+     * the column is outside of the world or has no solid block
+     */
+    pub fn surface (&self, x: i32, z: i32) -> Option<i32> {
+        return self.get(x, z).map(|c| c.surface).filter(|y| *y >= 0);
+    }
+
+    /**
+     * Returns how deep the water or lava at the top of x, z is, 0 if there is none
+     */
+    pub fn liquid_depth (&self, x: i32, z: i32) -> i32 {
+        return self.get(x, z).map_or(0, |c| c.liquid_depth);
+    }
+
+    /**
+     * True if the renderer lights the block at a position, which is the case for
+     * every block at or above the light depth and everything outside of the world
+     */
+    pub fn is_lit (&self, x: i32, y: i32, z: i32) -> bool {
+        return self.get(x, z).is_none_or(|c| y >= c.light_depth);
+    }
+
+    /**
+     * Rescans the column at x, z of tile_map, does nothing if it is outside of the world
+     */
+    pub fn update (&mut self, tile_map: &TileMap, x: i32, z: i32) {
+        if x < 0 || z < 0 || x >= self.width || z >= self.depth { return; }
+        self.columns[(x + z * self.width) as usize] = ColumnInfo::scan(tile_map, x, z);
+    }
+
+    /**
+     * Sets a block in tile_map and updates its column, returns false and
+     * changes nothing if the position is outside of the world
+     */
+    pub fn set (&mut self, tile_map: &mut TileMap, x: i32, y: i32, z: i32, block: Block) -> bool {
+        if !tile_map.set(x, y, z, block) { return false; }
+        self.update(tile_map, x, z);
+        return true;
+    }
+
+    /**
+     * Places the changed blocks of a level onto tile_map like JSLevel::apply_changed_blocks,
+     * then rescans only the columns that have a changed block. Returns how many changed
+     * blocks were outside of tile_map and skipped
+     */
+    pub fn apply_changed_blocks (&mut self, tile_map: &mut TileMap, level: &JSLevel) -> usize {
+        let skipped: usize = level.apply_changed_blocks(tile_map);
+
+        let mut rescanned: Vec<bool> = vec![false; self.columns.len()];
+        for pos in level.changedBlocks.keys() {
+            if pos.x < 0 || pos.z < 0 || pos.x >= self.width || pos.z >= self.depth { continue; }
+
+            let index: usize = (pos.x + pos.z * self.width) as usize;
+            if !rescanned[index] {
+                rescanned[index] = true;
+                self.update(tile_map, pos.x, pos.z);
+            }
+        }
+
+        return skipped;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block_pos::BlockPos;
+    use crate::random::Random;
+    use crate::{generate_tile_map, ChangedBlockMap, ChangedBlocks};
+
+    #[test]
+    fn scans_a_column_from_the_top () {
+        let mut tile_map: TileMap = TileMap::new(1, 16, 1);
+        for (y, block) in [(0, Block::Stone), (1, Block::Stone), (2, Block::Water), (3, Block::Water), (4, Block::Water), (6, Block::Glass)] {
+            tile_map.set(0, y, 0, block);
+        }
+
+        //Glass on top lets the light through, the water under it does not
+        assert_eq!(ColumnInfo::scan(&tile_map, 0, 0), ColumnInfo { highest: 6, surface: 6, liquid: None, liquid_depth: 0, light_depth: 4 });
+
+        tile_map.set(0, 6, 0, Block::Air);
+        assert_eq!(ColumnInfo::scan(&tile_map, 0, 0), ColumnInfo { highest: 4, surface: 1, liquid: Some(Block::Water), liquid_depth: 3, light_depth: 4 });
+
+        assert_eq!(ColumnInfo::scan(&TileMap::new(1, 16, 1), 0, 0), ColumnInfo { highest: -1, surface: -1, liquid: None, liquid_depth: 0, light_depth: 0 });
+    }
+
+    #[test]
+    fn incremental_updates_match_a_full_rebuild () {
+        let mut tile_map: TileMap = generate_tile_map(128, 3).unwrap();
+        let mut index: ColumnIndex = ColumnIndex::new(&tile_map);
+
+        //Blocks placed and dug all over the world, some of them outside of it
+        let mut random: Random = Random::new(99);
+        let blocks: [Block; 6] = [Block::Air, Block::Stone, Block::Water, Block::Lava, Block::Leaves, Block::Glass];
+        let mut changed_blocks: ChangedBlockMap = ChangedBlockMap::new();
+        for _ in 0..2000 {
+            let pos: BlockPos = BlockPos::new(random.next_int(140) - 6, random.next_int(70) - 3, random.next_int(140) - 6);
+            changed_blocks.insert(pos, ChangedBlocks::new(1, blocks[random.next_int(blocks.len() as i32) as usize]));
+        }
+        let level: JSLevel = JSLevel::new(3, changed_blocks, 128, 1);
+
+        assert!(index.apply_changed_blocks(&mut tile_map, &level) > 0);
+        assert_eq!(index, ColumnIndex::new(&tile_map));
+
+        index.set(&mut tile_map, 64, 63, 64, Block::Leaves);
+        index.set(&mut tile_map, 10, 0, 10, Block::Air);
+        assert!(!index.set(&mut tile_map, 128, 0, 0, Block::Stone));
+        assert_eq!(index, ColumnIndex::new(&tile_map));
+        assert!(!index.is_lit(64, 62, 64));
+    }
+}
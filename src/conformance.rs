@@ -0,0 +1,317 @@
+/*!
+ * Checks the generator against tile maps dumped from the js game.
+ *
+ * A Fixture records what one world should look like as a hash of every Y layer,
+ * so a test can hold hundreds of worlds in a few lines of text. A ConformanceReport
+ * compares a full reference tile map against this crate's output and sorts every
+ * mismatch by the kind of block involved and by region, which points at the stage
+ * that went wrong: a wrong ore means Carving drifted, a wrong tree means Planting did.
+ *
+ * Reference dumps are the worker's tile array for a world, either as raw bytes
+ * (one byte per tile in the flat layout) or as a json array of block ids.
+ */
+
+use crate::block::Block;
+use crate::error::{Error, Result};
+use crate::generator::{ClassicGenerator, Stage};
+use crate::tile_map::TileMap;
+use crate::WORLD_HEIGHT;
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+/**
+ * Fixture struct stores the expected output of the generator for one world
+ * world_size: width and depth of the world, 128, 256 or 512
+ * seed: seed of the world, same as worldSeed in a savedGame
+ * layer_hashes: FNV-1a hash of the block ids of every Y layer, from y = 0 upwards
+ *
+ * As text a fixture is one line of "size seed hash,hash,..." with the hashes in hex,
+ * lines starting with # are comments, see Fixture::parse_all
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Fixture {
+    pub world_size: i32,
+    pub seed: i64,
+    pub layer_hashes: Vec<u64>
+}
+
+impl Fixture {
+
+    /**
+     * Records a fixture from a tile map, such as one read from a js dump
+     */
+    pub fn from_tile_map (world_size: i32, seed: i64, tile_map: &TileMap) -> Self {
+        Fixture { world_size, seed, layer_hashes: layer_hashes(tile_map) }
+    }
+
+    /**
+     * Records a fixture from this crate's own output
+     */
+    pub fn generate (world_size: i32, seed: i64) -> Result<Self> {
+        let tile_map: TileMap = generate_classic(world_size, seed)?;
+        return Ok(Fixture::from_tile_map(world_size, seed, &tile_map));
+    }
+
+    /**
+     * Generates the world and returns the y of every layer that does not match
+     * the fixture, an empty list means the world matches
+     */
+    pub fn check (&self) -> Result<Vec<i32>> {
+        let tile_map: TileMap = generate_classic(self.world_size, self.seed)?;
+        return Ok(self.mismatched_layers(&tile_map));
+    }
+
+    /**
+     * Returns the y of every layer of tile_map that does not match the fixture
+     */
+    pub fn mismatched_layers (&self, tile_map: &TileMap) -> Vec<i32> {
+        let found: Vec<u64> = layer_hashes(tile_map);
+        let layers: usize = found.len().max(self.layer_hashes.len());
+
+        return (0..layers).filter(|y| found.get(*y) != self.layer_hashes.get(*y)).map(|y| y as i32).collect();
+    }
+
+    /**
+     * Parses every fixture in a text file, skipping blank lines and # comments
+     */
+    pub fn parse_all (text: &str) -> Result<Vec<Fixture>> {
+        return text.lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| line.parse())
+            .collect();
+    }
+}
+
+impl fmt::Display for Fixture {
+    fn fmt (&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let hashes: Vec<String> = self.layer_hashes.iter().map(|hash| format!("{:016x}", hash)).collect();
+        write!(f, "{} {} {}", self.world_size, self.seed, hashes.join(","))
+    }
+}
+
+impl FromStr for Fixture {
+    type Err = Error;
+
+    fn from_str (line: &str) -> Result<Fixture> {
+        let invalid = || Error::InvalidFixture(line.to_string());
+        let mut parts = line.split_whitespace();
+
+        let world_size: i32 = parts.next().and_then(|s| s.parse().ok()).ok_or_else(invalid)?;
+        let seed: i64 = parts.next().and_then(|s| s.parse().ok()).ok_or_else(invalid)?;
+        let hashes: &str = parts.next().ok_or_else(invalid)?;
+        if parts.next().is_some() { return Err(invalid()); }
+
+        let mut layer_hashes: Vec<u64> = Vec::new();
+        for hash in hashes.split(',') {
+            layer_hashes.push(u64::from_str_radix(hash, 16).map_err(|_| invalid())?);
+        }
+
+        return Ok(Fixture { world_size, seed, layer_hashes });
+    }
+}
+
+/**
+ * Hashes the block ids of every Y layer of a tile map with 64 bit FNV-1a,
+ * from y = 0 upwards
+ */
+pub fn layer_hashes (tile_map: &TileMap) -> Vec<u64> {
+    return tile_map.layers().map(|layer| {
+        let mut hash: u64 = 0xcbf29ce484222325;
+        for block in layer {
+            hash ^= block.id() as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+        hash
+    }).collect();
+}
+
+/**
+ * Reads a tile map dumped from the js game, given as raw bytes with one byte
+ * per tile, or as a json array of block ids
+ */
+pub fn read_tile_map_dump (bytes: &[u8], world_size: i32) -> Result<TileMap> {
+    let is_json: bool = bytes.iter().find(|b| !b.is_ascii_whitespace()) == Some(&b'[');
+
+    if is_json {
+        let tiles: Vec<u8> = serde_json::from_slice(bytes)?;
+        return TileMap::from_flat(world_size, WORLD_HEIGHT, world_size, &tiles);
+    }
+    return TileMap::from_flat(world_size, WORLD_HEIGHT, world_size, bytes);
+}
+
+fn generate_classic (world_size: i32, seed: i64) -> Result<TileMap> {
+    crate::check_world_size(world_size)?;
+    return ClassicGenerator::new(seed).size(world_size).generate();
+}
+
+/**
+ * MismatchKind enum sorts a mismatched tile by the blocks involved,
+ * checked in this order so a tile only counts once
+ * Ore: either block is an ore, placed while carving
+ * Tree: either block is a log or leaves, placed while planting
+ * Water: either block is water, placed while watering
+ * Lava: either block is lava, placed while melting
+ * Surface: either block is grass, sand or gravel, placed while growing
+ * Cave: one block is air and the other is not, carved while carving
+ * Terrain: anything else, such as stone against dirt from soiling
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum MismatchKind {
+    Ore,
+    Tree,
+    Water,
+    Lava,
+    Surface,
+    Cave,
+    Terrain
+}
+
+impl MismatchKind {
+
+    /**
+     * Sorts a tile where the reference has expected and the generator has found
+     */
+    pub fn classify (expected: Block, found: Block) -> Self {
+        let either = |f: fn(Block) -> bool| f(expected) || f(found);
+
+        if either(|b| matches!(b, Block::GoldOre | Block::IronOre | Block::CoalOre)) { return MismatchKind::Ore; }
+        if either(|b| matches!(b, Block::Log | Block::Leaves)) { return MismatchKind::Tree; }
+        if either(|b| b == Block::Water) { return MismatchKind::Water; }
+        if either(|b| b == Block::Lava) { return MismatchKind::Lava; }
+        if either(|b| matches!(b, Block::Grass | Block::Sand | Block::Gravel)) { return MismatchKind::Surface; }
+        if either(|b| b == Block::Air) { return MismatchKind::Cave; }
+        return MismatchKind::Terrain;
+    }
+
+    /**
+     * Returns the stage that places the blocks of this kind
+     */
+    pub fn stage (self) -> Stage {
+        match self {
+            MismatchKind::Ore => Stage::Carving,
+            MismatchKind::Tree => Stage::Planting,
+            MismatchKind::Water => Stage::Watering,
+            MismatchKind::Lava => Stage::Melting,
+            MismatchKind::Surface => Stage::Growing,
+            MismatchKind::Cave => Stage::Carving,
+            MismatchKind::Terrain => Stage::Soiling
+        }
+    }
+}
+
+/**
+ * Mismatch struct is a single tile that differs between the reference and the generator
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Mismatch {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+    pub expected: Block,
+    pub found: Block
+}
+
+/**
+ * ConformanceReport struct sums up every difference between a reference
+ * tile map and the generator's output
+ * tiles: number of tiles compared
+ * mismatches: number of tiles that differ
+ * by_kind: mismatches per MismatchKind
+ * by_region: mismatches per region of REGION_SIZE by REGION_SIZE columns, keyed by (x, z) / REGION_SIZE
+ * by_layer: mismatches per y
+ * samples: the first SAMPLES mismatches in the flat layout order
+ */
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ConformanceReport {
+    pub tiles: usize,
+    pub mismatches: usize,
+    pub by_kind: BTreeMap<MismatchKind, usize>,
+    pub by_region: BTreeMap<(i32, i32), usize>,
+    pub by_layer: BTreeMap<i32, usize>,
+    pub samples: Vec<Mismatch>
+}
+
+impl ConformanceReport {
+
+    //Width and depth in columns of each region mismatches are counted in
+    pub const REGION_SIZE: i32 = 32;
+
+    //Number of mismatches kept in samples
+    pub const SAMPLES: usize = 20;
+
+    /**
+     * Compares a reference tile map, such as a js dump, against the generator's
+     * output. Fails with MismatchedDimensions if the two are not the same size
+     */
+    pub fn compare (expected: &TileMap, found: &TileMap) -> Result<Self> {
+        let dimensions: (i32, i32, i32) = (expected.width(), expected.height(), expected.depth());
+        let found_dimensions: (i32, i32, i32) = (found.width(), found.height(), found.depth());
+        if dimensions != found_dimensions {
+            return Err(Error::MismatchedDimensions { expected: dimensions, found: found_dimensions });
+        }
+
+        let mut report: ConformanceReport = ConformanceReport { tiles: expected.blocks().len(), ..Default::default() };
+
+        for ((x, y, z, expected), found) in expected.positions().zip(found.blocks().iter().copied()) {
+            if expected == found { continue; }
+
+            report.mismatches += 1;
+            *report.by_kind.entry(MismatchKind::classify(expected, found)).or_default() += 1;
+            *report.by_region.entry((x / Self::REGION_SIZE, z / Self::REGION_SIZE)).or_default() += 1;
+            *report.by_layer.entry(y).or_default() += 1;
+
+            if report.samples.len() < Self::SAMPLES {
+                report.samples.push(Mismatch { x, y, z, expected, found });
+            }
+        }
+
+        return Ok(report);
+    }
+
+    /**
+     * Generates the world for a seed and compares it against a reference tile map
+     */
+    pub fn check (world_size: i32, seed: i64, expected: &TileMap) -> Result<Self> {
+        let found: TileMap = generate_classic(world_size, seed)?;
+        return ConformanceReport::compare(expected, &found);
+    }
+
+    pub fn is_match (&self) -> bool {
+        return self.mismatches == 0;
+    }
+}
+
+impl fmt::Display for ConformanceReport {
+    fn fmt (&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_match() {
+            return writeln!(f, "all {} tiles match", self.tiles);
+        }
+
+        writeln!(f, "{} of {} tiles differ", self.mismatches, self.tiles)?;
+
+        writeln!(f, "by kind:")?;
+        for (kind, count) in &self.by_kind {
+            writeln!(f, "  {:?} ({}) {}", kind, kind.stage(), count)?;
+        }
+
+        writeln!(f, "by region of {0}x{0} columns:", Self::REGION_SIZE)?;
+        for ((x, z), count) in &self.by_region {
+            writeln!(f, "  x {}..{} z {}..{} {}", x * Self::REGION_SIZE, (x + 1) * Self::REGION_SIZE, z * Self::REGION_SIZE, (z + 1) * Self::REGION_SIZE, count)?;
+        }
+
+        writeln!(f, "by layer:")?;
+        for (y, count) in &self.by_layer {
+            writeln!(f, "  y {} {}", y, count)?;
+        }
+
+        writeln!(f, "first mismatches:")?;
+        for m in &self.samples {
+            writeln!(f, "  {} {} {} expected {:?} found {:?}", m.x, m.y, m.z, m.expected, m.found)?;
+        }
+
+        return Ok(());
+    }
+}
@@ -0,0 +1,160 @@
+use crate::block_pos::BlockPos;
+use crate::error::{Error, Result};
+use crate::tile_map::TileMap;
+use crate::{ChangedBlockMap, ChangedBlocks};
+
+/**
+ * ChangePolicy decides which blocks of an edited world are written
+ * into changedBlocks when it is compared against natural generation
+ * All: every block is written, the save becomes huge and lags the game (was opt 0)
+ * KeepExisting: blocks that differ from natural generation, plus any block
+ * that already has an entry in changedBlocks (was opt 1)
+ * Minimal: only blocks that differ from natural generation (was opt 2)
+ */
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ChangePolicy {
+    All,
+    KeepExisting,
+    #[default]
+    Minimal
+}
+
+impl ChangePolicy {
+
+    /**
+     * Decides whether a block is written, differs is true if the
+     * block does not match natural generation and existing is true
+     * if the block already has an entry in changedBlocks
+     */
+    pub fn includes (self, differs: bool, existing: bool) -> bool {
+        match self {
+            ChangePolicy::All => true,
+            ChangePolicy::KeepExisting => differs || existing,
+            ChangePolicy::Minimal => differs
+        }
+    }
+}
+
+//Converts the old opt values, 0 = All, 1 = KeepExisting, 2 = Minimal
+impl TryFrom<u8> for ChangePolicy {
+    type Error = Error;
+
+    fn try_from (opt: u8) -> Result<ChangePolicy> {
+        match opt {
+            0 => Ok(ChangePolicy::All),
+            1 => Ok(ChangePolicy::KeepExisting),
+            2 => Ok(ChangePolicy::Minimal),
+            _ => Err(Error::InvalidChangePolicy(opt))
+        }
+    }
+}
+
+/**
+ * Compares an edited tile map against the naturally generated one and
+ * returns the changedBlocks for it. existing is the changedBlocks the
+ * level already has, which only matters for ChangePolicy::KeepExisting.
+ * Entries are ordered the same way the tile map is laid out, and a is
+ * 1 for every block that differs from natural generation
+ */
+pub fn diff_tile_maps (natural: &TileMap, edited: &TileMap, existing: &ChangedBlockMap, policy: ChangePolicy) -> Result<ChangedBlockMap> {
+
+    let expected: (i32, i32, i32) = (natural.width(), natural.height(), natural.depth());
+    let found: (i32, i32, i32) = (edited.width(), edited.height(), edited.depth());
+    if expected != found {
+        return Err(Error::MismatchedDimensions { expected, found });
+    }
+
+    let mut changed_blocks: ChangedBlockMap = ChangedBlockMap::new();
+
+    for (x, y, z, block) in edited.positions() {
+        let pos: BlockPos = BlockPos::new(x, y, z);
+        let differs: bool = natural.get(x, y, z) != Some(block);
+
+        if policy.includes(differs, existing.contains_key(&pos)) {
+            changed_blocks.insert(pos, ChangedBlocks::new(differs as u8, block));
+        }
+    }
+
+    return Ok(changed_blocks);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::Block;
+
+    //A 2x2x2 world of stone with dirt on top
+    fn natural () -> TileMap {
+        let mut tile_map: TileMap = TileMap::new(2, 2, 2);
+        for (x, z) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+            tile_map.set(x, 0, z, Block::Stone);
+            tile_map.set(x, 1, z, Block::Dirt);
+        }
+        return tile_map;
+    }
+
+    #[test]
+    fn converts_the_old_opt_values () {
+        assert_eq!(ChangePolicy::try_from(0).unwrap(), ChangePolicy::All);
+        assert_eq!(ChangePolicy::try_from(1).unwrap(), ChangePolicy::KeepExisting);
+        assert_eq!(ChangePolicy::try_from(2).unwrap(), ChangePolicy::Minimal);
+        for opt in [3, 4, 255] {
+            assert!(matches!(ChangePolicy::try_from(opt), Err(Error::InvalidChangePolicy(o)) if o == opt));
+        }
+        assert_eq!(ChangePolicy::default(), ChangePolicy::Minimal);
+    }
+
+    #[test]
+    fn minimal_only_keeps_blocks_that_differ () {
+        let mut edited: TileMap = natural();
+        edited.set(1, 1, 0, Block::Glass);
+        edited.set(0, 0, 1, Block::Air);
+
+        let changed: ChangedBlockMap = diff_tile_maps(&natural(), &edited, &ChangedBlockMap::new(), ChangePolicy::Minimal).unwrap();
+
+        //Ordered the way the tile map is laid out, Y then Z then X
+        let expected: Vec<(BlockPos, ChangedBlocks)> = vec![
+            (BlockPos::new(0, 0, 1), ChangedBlocks::new(1, Block::Air)),
+            (BlockPos::new(1, 1, 0), ChangedBlocks::new(1, Block::Glass))
+        ];
+        assert_eq!(changed.into_iter().collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn keep_existing_also_keeps_unchanged_entries () {
+        let mut edited: TileMap = natural();
+        edited.set(1, 1, 1, Block::Sand);
+
+        let mut existing: ChangedBlockMap = ChangedBlockMap::new();
+        existing.insert(BlockPos::new(0, 1, 0), ChangedBlocks::new(1, Block::Glass));
+
+        let changed: ChangedBlockMap = diff_tile_maps(&natural(), &edited, &existing, ChangePolicy::KeepExisting).unwrap();
+
+        assert_eq!(changed.len(), 2);
+        assert_eq!(changed[&BlockPos::new(0, 1, 0)], ChangedBlocks::new(0, Block::Dirt));
+        assert_eq!(changed[&BlockPos::new(1, 1, 1)], ChangedBlocks::new(1, Block::Sand));
+    }
+
+    #[test]
+    fn all_writes_every_block () {
+        let mut edited: TileMap = natural();
+        edited.set(0, 1, 0, Block::Bricks);
+
+        let changed: ChangedBlockMap = diff_tile_maps(&natural(), &edited, &ChangedBlockMap::new(), ChangePolicy::All).unwrap();
+
+        assert_eq!(changed.len(), 8);
+        assert_eq!(changed.values().filter(|c| c.a == 1).count(), 1);
+        assert_eq!(changed[&BlockPos::new(0, 1, 0)], ChangedBlocks::new(1, Block::Bricks));
+    }
+
+    #[test]
+    fn rejects_tile_maps_of_different_sizes () {
+        let result: Result<ChangedBlockMap> = diff_tile_maps(&natural(), &TileMap::new(2, 3, 2), &ChangedBlockMap::new(), ChangePolicy::Minimal);
+        assert!(matches!(result, Err(Error::MismatchedDimensions { expected: (2, 2, 2), found: (2, 3, 2) })));
+
+        //Same number of tiles in another shape
+        let result: Result<ChangedBlockMap> = diff_tile_maps(&TileMap::new(4, 2, 8), &TileMap::new(8, 2, 4), &ChangedBlockMap::new(), ChangePolicy::Minimal);
+        let error: Error = result.unwrap_err();
+        assert_eq!(error.to_string(), "tile map is 8x2x4, expected 4x2x8");
+    }
+}
@@ -0,0 +1,98 @@
+use std::fmt;
+
+/**
+ * Error enum covers everything that can go wrong inside the library,
+ * every public function returns it instead of panicking
+ * Sqlite: the localStorage db could not be opened, read or written
+ * Json: a savedGame string is not valid json or not in the savedGame format
+ * Snappy: a localStorage value could not be compressed or decompressed
+ * Io: a file could not be written
+ * MissingSavedGame: the localStorage db has no savedGame key
+ * InvalidWorldSize: world size is not one the generator supports
+ * InvalidDimensions: generator dimensions are outside of what ClassicGenerator accepts
+ * InvalidTileMap: a tile map does not have the length its dimensions require
+ * MismatchedDimensions: two tile maps that are compared are not the same width, height and depth
+ * UnknownBlock: a block id is not part of the classic js palette
+ * InvalidBlockPos: a changedBlocks key is not in the p{x}_{y}_{z} format
+ * InvalidChangePolicy: an opt value other than 0, 1 or 2
+ * InvalidFixture: a conformance fixture line is not in the size seed hashes format
+ * InvalidCheckpoint: a seed search checkpoint has a line it does not understand or is missing one
+ * InvalidObservation: an observation for seed cracking is empty or outside of its world
+ * UnsupportedCharacter: a savedGame string has a character above U+00FF, which the
+ * localStorage value stores in a single byte
+ * Cancelled: world generation was stopped through a CancelToken
+ */
+#[derive(Debug)]
+pub enum Error {
+    Sqlite(rusqlite::Error),
+    Json(serde_json::Error),
+    Snappy(snap::Error),
+    Io(std::io::Error),
+    MissingSavedGame,
+    InvalidWorldSize(i32),
+    InvalidDimensions { width: i32, height: i32, depth: i32 },
+    InvalidTileMap { expected: usize, found: usize },
+    MismatchedDimensions { expected: (i32, i32, i32), found: (i32, i32, i32) },
+    UnknownBlock(u8),
+    InvalidBlockPos(String),
+    InvalidChangePolicy(u8),
+    InvalidFixture(String),
+    InvalidCheckpoint(String),
+    InvalidObservation(String),
+    UnsupportedCharacter(char),
+    Cancelled
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt (&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Sqlite(e) => write!(f, "sqlite error: {}", e),
+            Error::Json(e) => write!(f, "json error: {}", e),
+            Error::Snappy(e) => write!(f, "snappy error: {}", e),
+            Error::Io(e) => write!(f, "io error: {}", e),
+            Error::MissingSavedGame => write!(f, "no savedGame found in localStorage"),
+            Error::InvalidWorldSize(size) => write!(f, "invalid world size {}, must be 128, 256 or 512", size),
+            Error::InvalidDimensions { width, height, depth } => write!(f, "invalid world dimensions {}x{}x{}, width and depth must be 1 to 1024 and height 1 to 256", width, height, depth),
+            Error::InvalidTileMap { expected, found } => write!(f, "tile map has {} tiles, expected {}", found, expected),
+            Error::MismatchedDimensions { expected, found } => write!(f, "tile map is {}x{}x{}, expected {}x{}x{}", found.0, found.1, found.2, expected.0, expected.1, expected.2),
+            Error::UnknownBlock(id) => write!(f, "unknown block id {}", id),
+            Error::InvalidBlockPos(key) => write!(f, "invalid block position {:?}, expected p{{x}}_{{y}}_{{z}}", key),
+            Error::InvalidChangePolicy(opt) => write!(f, "invalid change policy {}, must be 0, 1 or 2", opt),
+            Error::InvalidFixture(line) => write!(f, "invalid fixture {:?}, expected a size, a seed and comma separated layer hashes", line),
+            Error::InvalidCheckpoint(line) => write!(f, "invalid seed search checkpoint line {:?}", line),
+            Error::InvalidObservation(reason) => write!(f, "invalid observation, {}", reason),
+            Error::UnsupportedCharacter(ch) => write!(f, "character {:?} (U+{:04X}) can not be stored in a localStorage value, only U+0000 to U+00FF can", ch, *ch as u32),
+            Error::Cancelled => write!(f, "world generation was cancelled")
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source (&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Sqlite(e) => Some(e),
+            Error::Json(e) => Some(e),
+            Error::Snappy(e) => Some(e),
+            Error::Io(e) => Some(e),
+            _ => None
+        }
+    }
+}
+
+impl From<rusqlite::Error> for Error {
+    fn from (e: rusqlite::Error) -> Self { Error::Sqlite(e) }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from (e: serde_json::Error) -> Self { Error::Json(e) }
+}
+
+impl From<snap::Error> for Error {
+    fn from (e: snap::Error) -> Self { Error::Snappy(e) }
+}
+
+impl From<std::io::Error> for Error {
+    fn from (e: std::io::Error) -> Self { Error::Io(e) }
+}
@@ -0,0 +1,432 @@
+use crate::block::Block;
+use crate::error::{Error, Result};
+use crate::pipeline::{classic_stages, GenerationStage};
+use crate::preview::ApproximatePreview;
+use crate::random_level_worker;
+use crate::settings::GenerationSettings;
+use crate::tile_map::TileMap;
+use crate::trace::GenerationTrace;
+use crate::world_generator::WorldGenerator;
+use crate::WORLD_HEIGHT;
+
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+/**
+ * Stage enum names the steps of world generation, in the order they run.
+ * These are the stages the js worker reports to the loading screen
+ * Raising: the height map is built from noise
+ * Eroding: parts of the height map are flattened into terraces
+ * Soiling: the height map is filled in with stone and dirt
+ * Carving: caves are carved out and ores are placed
+ * Watering: oceans and lakes are flooded with water
+ * Melting: caves are flooded with lava
+ * Growing: grass, sand and gravel are placed on the surface
+ * Planting: trees are planted
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Stage {
+    Raising,
+    Eroding,
+    Soiling,
+    Carving,
+    Watering,
+    Melting,
+    Growing,
+    Planting
+}
+
+impl Stage {
+
+    pub const ALL: [Stage; 8] = [Stage::Raising, Stage::Eroding, Stage::Soiling, Stage::Carving, Stage::Watering, Stage::Melting, Stage::Growing, Stage::Planting];
+
+    /**
+     * Returns the text the game shows on the loading screen for this stage
+     */
+    pub fn name (self) -> &'static str {
+        match self {
+            Stage::Raising => "Raising..",
+            Stage::Eroding => "Eroding..",
+            Stage::Soiling => "Soiling..",
+            Stage::Carving => "Carving..",
+            Stage::Watering => "Watering..",
+            Stage::Melting => "Melting..",
+            Stage::Growing => "Growing..",
+            Stage::Planting => "Planting.."
+        }
+    }
+}
+
+impl fmt::Display for Stage {
+    fn fmt (&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/**
+ * CancelToken lets another thread stop a world that is still generating.
+ * Clones share the same flag, so keep one and give a clone to the generator,
+ * calling cancel on either makes the generator return Error::Cancelled
+ */
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>
+}
+
+impl CancelToken {
+
+    pub fn new () -> Self {
+        return CancelToken::default();
+    }
+
+    pub fn cancel (&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled (&self) -> bool {
+        return self.cancelled.load(Ordering::Relaxed);
+    }
+}
+
+/**
+ * ClassicGenerator is a builder for the classic js world generator
+ * that accepts any width, depth and height, for example:
+ * ClassicGenerator::new(seed).width(300).depth(200).height(96).generate()
+ * seed: seed of the world, same as worldSeed in a savedGame
+ * width: size along X, defaults to 256 like the game
+ * depth: size along Z, defaults to 256 like the game
+ * height: size along Y, defaults to 64 like the game
+ * cancel: checked while generating, see CancelToken
+ * threads: threads used for the noise of the height map stages, defaults to 1
+ * settings: tuning constants such as ore, cave and tree density, see GenerationSettings
+ * stages: the stages run in order, the game's own eight unless changed, see GenerationStage
+ * For the sizes the game offers (128, 256 or 512 square and 64 high) with the default
+ * settings and stages the output is exactly what the game generates. For any other size the same stages run,
+ * but water and lava are flooded from the real sea level and cave positions
+ * instead of the offsets the game needs for its own sizes
+ */
+#[derive(Clone, Debug)]
+pub struct ClassicGenerator {
+    seed: i64,
+    width: i32,
+    depth: i32,
+    height: i32,
+    cancel: CancelToken,
+    threads: usize,
+    settings: GenerationSettings,
+    stages: Vec<Arc<dyn GenerationStage>>,
+    custom_stages: bool
+}
+
+impl ClassicGenerator {
+
+    //Largest width or depth the generator accepts
+    pub const MAX_SIZE: i32 = 1024;
+
+    //Largest height the generator accepts
+    pub const MAX_HEIGHT: i32 = 256;
+
+    pub fn new (seed: i64) -> Self {
+        ClassicGenerator { seed, width: 256, depth: 256, height: WORLD_HEIGHT, cancel: CancelToken::new(), threads: 1, settings: GenerationSettings::default(), stages: classic_stages(), custom_stages: false }
+    }
+
+    /**
+     * Sets both the width and the depth, the same as worldSize in a savedGame
+     */
+    pub fn size (mut self, world_size: i32) -> Self {
+        self.width = world_size;
+        self.depth = world_size;
+        return self;
+    }
+
+    pub fn width (mut self, width: i32) -> Self {
+        self.width = width;
+        return self;
+    }
+
+    pub fn depth (mut self, depth: i32) -> Self {
+        self.depth = depth;
+        return self;
+    }
+
+    pub fn height (mut self, height: i32) -> Self {
+        self.height = height;
+        return self;
+    }
+
+    /**
+     * Sets the token that can stop generation early. The long loops of every
+     * stage check it, so a cancelled 512 world stops within a moment
+     */
+    pub fn cancel_token (mut self, cancel: CancelToken) -> Self {
+        self.cancel = cancel;
+        return self;
+    }
+
+    /**
+     * Sets how many threads the noise of the Raising, Eroding, Soiling and Growing stages is
+     * spread over, 0 uses every core. That noise never draws from the seed's random, and the
+     * stages that do always run on one thread, so the world is the same for any thread count
+     */
+    pub fn threads (mut self, threads: usize) -> Self {
+        self.threads = threads;
+        return self;
+    }
+
+    /**
+     * Sets the tuning constants, the default reproduces the game
+     */
+    pub fn settings (mut self, settings: GenerationSettings) -> Self {
+        self.settings = settings;
+        return self;
+    }
+
+    /**
+     * Replaces the whole list of stages, to reorder them or run a list of your own.
+     * Start from classic_stages() to keep the game's stages
+     */
+    pub fn stages (mut self, stages: Vec<Arc<dyn GenerationStage>>) -> Self {
+        self.stages = stages;
+        self.custom_stages = true;
+        return self;
+    }
+
+    /**
+     * Adds a stage to run right after the last stage that reports as after,
+     * or at the end if there is none, for example dungeons after Carving
+     */
+    pub fn stage_after<S: GenerationStage + 'static> (mut self, after: Stage, stage: S) -> Self {
+        let index: usize = self.stages.iter().rposition(|s| s.stage() == after).map(|i| i + 1).unwrap_or(self.stages.len());
+        self.stages.insert(index, Arc::new(stage));
+        self.custom_stages = true;
+        return self;
+    }
+
+    /**
+     * Runs replacement in place of every stage that reports as stage, at the
+     * position of the first one. The list is unchanged if no stage reports as it
+     */
+    pub fn replace_stage<S: GenerationStage + 'static> (mut self, stage: Stage, replacement: S) -> Self {
+        if let Some(index) = self.stages.iter().position(|s| s.stage() == stage) {
+            self.stages.retain(|s| s.stage() != stage);
+            self.stages.insert(index, Arc::new(replacement));
+            self.custom_stages = true;
+        }
+        return self;
+    }
+
+    pub fn seed (&self) -> i64 {self.seed}
+
+    //Returns the (width, height, depth) the generator will produce
+    pub fn dimensions (&self) -> (i32, i32, i32) {(self.width, self.height, self.depth)}
+
+    pub fn generation_settings (&self) -> &GenerationSettings {&self.settings}
+
+    pub fn generation_stages (&self) -> &[Arc<dyn GenerationStage>] {&self.stages}
+
+    /**
+     * True if the dimensions are ones the game offers and the settings and stages are
+     * the defaults, in which case the output matches the game block for block
+     */
+    pub fn is_classic (&self) -> bool {
+        return self.width == self.depth && self.height == WORLD_HEIGHT && matches!(self.width, 128 | 256 | 512) && self.settings.is_classic() && !self.custom_stages;
+    }
+
+    /**
+     * Runs the generator and returns the world, fails with InvalidDimensions if the
+     * width or depth is not within 1..=MAX_SIZE or the height is not within 1..=MAX_HEIGHT,
+     * and with Cancelled if the cancel token was cancelled before the world was done
+     */
+    pub fn generate (&self) -> Result<TileMap> {
+        return self.generate_with_progress(|_, _| ());
+    }
+
+    /**
+     * Same as generate, but calls progress with the current stage and a percentage
+     * from 0 to 100 at the same points the js worker reports progress. Every stage
+     * starts with a call at 0%, and the last call is Planting at 100%
+     */
+    pub fn generate_with_progress<F: FnMut(Stage, u8)> (&self, mut progress: F) -> Result<TileMap> {
+        return self.run(&mut progress, None);
+    }
+
+    /**
+     * Same as generate, but also returns a GenerationTrace of the height maps, caves,
+     * ore veins, water, lava and trees each stage made along the way. Tracing costs
+     * one flag per tile for the cave mask on top of the world itself
+     */
+    pub fn generate_traced (&self) -> Result<(TileMap, GenerationTrace)> {
+        let mut trace: GenerationTrace = GenerationTrace::new(self.width, self.height, self.depth);
+        let tile_map: TileMap = self.run(&mut |_, _| (), Some(&mut trace))?;
+        return Ok((tile_map, trace));
+    }
+
+    /**
+     * Sketches the world without generating it, for browsing seeds. Only Raising and
+     * Eroding run, for every step-th column along X and Z, the ground and beaches are
+     * estimated from their height map and everything below the sea level is taken as
+     * water. A step below 1 is taken as 1, and one above the width and depth as the
+     * larger of the two, which samples a single column. The result is approximate, see
+     * ApproximatePreview for what it leaves out
+     */
+    pub fn preview (&self, step: i32) -> Result<ApproximatePreview> {
+        return self.preview_with_progress(step, |_, _| ());
+    }
+
+    /**
+     * Same as preview, but calls progress with a percentage, all under Stage::Raising
+     */
+    pub fn preview_with_progress<F: FnMut(Stage, u8)> (&self, step: i32, mut progress: F) -> Result<ApproximatePreview> {
+        check_dimensions(self.width, self.height, self.depth)?;
+
+        //A step past the edge of the world samples the same single column
+        let step: i32 = step.clamp(1, self.width.max(self.depth));
+        let columns: Vec<(f64, f64, bool)> = random_level_worker::start_preview(self.width, self.depth, self.height, self.seed, step, &mut progress, &self.cancel, self.thread_count(), &self.settings).ok_or(Error::Cancelled)?;
+        return Ok(ApproximatePreview::from_columns(self.width, self.height, self.depth, step, self.settings.sea_level_for(self.height), &columns));
+    }
+
+    fn run (&self, progress: &mut dyn FnMut(Stage, u8), trace: Option<&mut GenerationTrace>) -> Result<TileMap> {
+        check_dimensions(self.width, self.height, self.depth)?;
+
+        let level: Vec<u8> = random_level_worker::start_generation(self.width, self.depth, self.height, self.seed, progress, &self.cancel, trace, self.thread_count(), &self.settings, &self.stages).ok_or(Error::Cancelled)?; //Generating all tiles in the world, in the TileMap layout
+        let mut tile_map: TileMap = TileMap::new(self.width, self.height, self.depth);
+
+        for (block, id) in tile_map.blocks_mut().iter_mut().zip(level) {
+            *block = Block::from_id(id).unwrap_or(Block::Air); //Copying tiles to tile map
+        }
+
+        return Ok(tile_map);
+    }
+
+    fn thread_count (&self) -> usize {
+        if self.threads == 0 {
+            return thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+        }
+        return self.threads;
+    }
+
+    /**
+     * Runs the generator on a new thread, for callers that cannot block. Progress is
+     * sent through the returned receiver as it happens, which is closed once generation
+     * finishes, and the world is returned when the handle is joined
+     */
+    pub fn spawn (&self) -> (Receiver<(Stage, u8)>, JoinHandle<Result<TileMap>>) {
+        let (sender, receiver) = mpsc::channel();
+        let generator: ClassicGenerator = self.clone();
+
+        let handle = thread::spawn(move || {
+            //A dropped receiver only means nobody is listening, generation carries on
+            return generator.generate_with_progress(|stage, percent| { let _ = sender.send((stage, percent)); });
+        });
+
+        return (receiver, handle);
+    }
+}
+
+/**
+ * Runs the classic generator with the threads, cancel token and settings of this
+ * builder, but with the dimensions and seed passed in
+ */
+impl WorldGenerator for ClassicGenerator {
+    fn generate_world (&self, width: i32, height: i32, depth: i32, seed: i64) -> Result<TileMap> {
+        let generator: ClassicGenerator = ClassicGenerator { seed, ..self.clone() };
+        return generator.width(width).height(height).depth(depth).generate();
+    }
+}
+
+/**
+ * Checks that dimensions are within 1..=MAX_SIZE for the width and depth and
+ * 1..=MAX_HEIGHT for the height, the limits every built in generator shares
+ */
+pub(crate) fn check_dimensions (width: i32, height: i32, depth: i32) -> Result<()> {
+    if !(1..=ClassicGenerator::MAX_SIZE).contains(&width) || !(1..=ClassicGenerator::MAX_SIZE).contains(&depth) || !(1..=ClassicGenerator::MAX_HEIGHT).contains(&height) {
+        return Err(Error::InvalidDimensions { width, height, depth });
+    }
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_to_the_game_world () {
+        let generator: ClassicGenerator = ClassicGenerator::new(5);
+        assert_eq!(generator.seed(), 5);
+        assert_eq!(generator.dimensions(), (256, WORLD_HEIGHT, 256));
+        assert!(generator.is_classic());
+        assert!(ClassicGenerator::new(5).size(128).is_classic());
+        assert!(!ClassicGenerator::new(5).size(200).is_classic());
+        assert!(!ClassicGenerator::new(5).width(128).depth(256).is_classic());
+        assert!(!ClassicGenerator::new(5).height(96).is_classic());
+    }
+
+    #[test]
+    fn generates_any_dimensions () {
+        let tile_map: TileMap = ClassicGenerator::new(42).width(40).depth(24).height(48).generate().unwrap();
+        assert_eq!((tile_map.width(), tile_map.height(), tile_map.depth()), (40, 48, 24));
+        assert!(tile_map.blocks().contains(&Block::Stone));
+        assert_eq!(ClassicGenerator::new(42).width(40).depth(24).height(48).generate().unwrap(), tile_map);
+    }
+
+    #[test]
+    fn progress_runs_through_the_stages_in_order () {
+        let mut events: Vec<(Stage, u8)> = Vec::new();
+        let tile_map: TileMap = ClassicGenerator::new(3).size(128).generate_with_progress(|stage, percent| events.push((stage, percent))).unwrap();
+
+        for pair in events.windows(2) {
+            let ((stage, percent), (next_stage, next_percent)) = (pair[0], pair[1]);
+            assert!(stage <= next_stage, "{} reported after {}", next_stage, stage);
+            if stage == next_stage {
+                assert!(percent <= next_percent, "{} went from {}% to {}%", stage, percent, next_percent);
+            }
+        }
+        assert!(events.iter().all(|(_, percent)| *percent <= 100));
+        for stage in Stage::ALL {
+            assert!(events.contains(&(stage, 0)), "{} does not start at 0%", stage);
+        }
+        assert_eq!(events.last(), Some(&(Stage::Planting, 100)));
+
+        //spawn sends the same events, then the world comes back through the handle
+        let (receiver, handle) = ClassicGenerator::new(3).size(128).spawn();
+        let sent: Vec<(Stage, u8)> = receiver.iter().collect();
+        assert_eq!(sent, events);
+        assert_eq!(handle.join().unwrap().unwrap(), tile_map);
+    }
+
+    #[test]
+    fn cancelled_token_stops_generation () {
+        let cancel: CancelToken = CancelToken::new();
+        cancel.cancel();
+        let generator: ClassicGenerator = ClassicGenerator::new(1).size(128).cancel_token(cancel.clone());
+
+        assert!(matches!(generator.generate(), Err(Error::Cancelled)));
+        assert!(matches!(generator.preview(4), Err(Error::Cancelled)));
+    }
+
+    #[test]
+    fn cancelling_mid_way_stops_at_the_next_check () {
+        let cancel: CancelToken = CancelToken::new();
+        let generator: ClassicGenerator = ClassicGenerator::new(1).width(64).depth(64).cancel_token(cancel.clone());
+
+        let mut stages: Vec<Stage> = Vec::new();
+        let result: Result<TileMap> = generator.generate_with_progress(|stage, _| {
+            if stages.last() != Some(&stage) { stages.push(stage); }
+            if stage == Stage::Carving { cancel.cancel(); }
+        });
+
+        assert!(matches!(result, Err(Error::Cancelled)));
+        assert_eq!(stages.last(), Some(&Stage::Carving));
+    }
+
+    #[test]
+    fn rejects_dimensions_out_of_range () {
+        for (width, height, depth) in [(0, 64, 128), (128, 0, 128), (128, 64, 0), (1025, 64, 128), (128, 257, 128), (-1, 64, 128)] {
+            let result: Result<TileMap> = ClassicGenerator::new(1).width(width).height(height).depth(depth).generate();
+            assert!(matches!(result, Err(Error::InvalidDimensions { width: w, height: h, depth: d }) if (w, h, d) == (width, height, depth)));
+        }
+    }
+}
@@ -0,0 +1,648 @@
+/*!
+ * Math the way the browser does it, so generation matches classic js bit for bit.
+ *
+ * sin and cos are ported from fdlibm 5.3, which is what V8 (src/base/ieee754.cc)
+ * and SpiderMonkey use for Math.sin and Math.cos. Rust's f64::sin and f64::cos
+ * call the platform libm, which is allowed to round the last bit differently.
+ *
+ * parse_int is parseInt called with a number, which first turns the number into
+ * a string. Numbers below 1e-6 or from 1e21 up are written in exponent form, so
+ * parseInt(1e-7) reads "1e-7" and returns 1 where a cast would give 0.
+ */
+
+//Constants and loops are written as they are in fdlibm so they can be checked against the C source
+#![allow(clippy::approx_constant, clippy::excessive_precision, clippy::explicit_counter_loop, clippy::eq_op)]
+
+/*************************
+  Word access
+*************************/
+
+//The upper 32 bits of a double, fdlibm's GET_HIGH_WORD
+fn high_word (x: f64) -> i32 {
+    return (x.to_bits() >> 32) as u32 as i32;
+}
+
+//The lower 32 bits of a double, fdlibm's GET_LOW_WORD
+fn low_word (x: f64) -> u32 {
+    return x.to_bits() as u32;
+}
+
+//fdlibm's INSERT_WORDS
+fn from_words (high: u32, low: u32) -> f64 {
+    return f64::from_bits(((high as u64) << 32) | low as u64);
+}
+
+//x * 2^n, exact unless the result overflows or is subnormal like C's scalbn
+fn scalbn (mut x: f64, mut n: i32) -> f64 {
+    //Steps of at most 2^1000 keep every factor representable
+    while n > 1000 {
+        x *= f64::from_bits(((1023 + 1000) as u64) << 52);
+        n -= 1000;
+    }
+    while n < -1000 {
+        x *= f64::from_bits(((1023 - 1000) as u64) << 52);
+        n += 1000;
+    }
+    return x * f64::from_bits(((1023 + n) as u64) << 52);
+}
+
+/*************************
+  Kernel functions
+*************************/
+
+const S1: f64 = -1.66666666666666324348e-01;
+const S2: f64 = 8.33333333332248946124e-03;
+const S3: f64 = -1.98412698298579493134e-04;
+const S4: f64 = 2.75573137070700676789e-06;
+const S5: f64 = -2.50507602534068634195e-08;
+const S6: f64 = 1.58969099521155010221e-10;
+
+//sin on [-pi/4, pi/4], y is the tail of x and iy is 0 if there is no tail
+fn kernel_sin (x: f64, y: f64, iy: i32) -> f64 {
+    let ix: i32 = high_word(x) & 0x7fffffff;
+    if ix < 0x3e400000 && x as i32 == 0 {
+        return x;
+    }
+
+    let z: f64 = x * x;
+    let v: f64 = z * x;
+    let r: f64 = S2 + z * (S3 + z * (S4 + z * (S5 + z * S6)));
+
+    if iy == 0 {
+        return x + v * (S1 + z * r);
+    }
+    return x - ((z * (0.5 * y - v * r) - y) - v * S1);
+}
+
+const C1: f64 = 4.16666666666666019037e-02;
+const C2: f64 = -1.38888888888741095749e-03;
+const C3: f64 = 2.48015872894767294178e-05;
+const C4: f64 = -2.75573143513906633035e-07;
+const C5: f64 = 2.08757232129817482790e-09;
+const C6: f64 = -1.13596475577881948265e-11;
+
+//cos on [-pi/4, pi/4], y is the tail of x
+fn kernel_cos (x: f64, y: f64) -> f64 {
+    let ix: i32 = high_word(x) & 0x7fffffff;
+    if ix < 0x3e400000 && x as i32 == 0 {
+        return 1.0;
+    }
+
+    let z: f64 = x * x;
+    let r: f64 = z * (C1 + z * (C2 + z * (C3 + z * (C4 + z * (C5 + z * C6)))));
+
+    if ix < 0x3fd33333 {
+        return 1.0 - (0.5 * z - (z * r - x * y));
+    }
+
+    let qx: f64 = if ix > 0x3fe90000 {0.28125} else {from_words((ix - 0x00200000) as u32, 0)};
+    let hz: f64 = 0.5 * z - qx;
+    let a: f64 = 1.0 - qx;
+    return a - (hz - (z * r - x * y));
+}
+
+/*************************
+  Argument reduction
+*************************/
+
+//24 bit chunks of 2/pi
+const TWO_OVER_PI: [i32; 66] = [
+    0xA2F983, 0x6E4E44, 0x1529FC, 0x2757D1, 0xF534DD, 0xC0DB62,
+    0x95993C, 0x439041, 0xFE5163, 0xABDEBB, 0xC561B7, 0x246E3A,
+    0x424DD2, 0xE00649, 0x2EEA09, 0xD1921C, 0xFE1DEB, 0x1CB129,
+    0xA73EE8, 0x8235F5, 0x2EBB44, 0x84E99C, 0x7026B4, 0x5F7E41,
+    0x3991D6, 0x398353, 0x39F49C, 0x845F8B, 0xBDF928, 0x3B1FF8,
+    0x97FFDE, 0x05980F, 0xEF2F11, 0x8B5A0A, 0x6D1F6D, 0x367ECF,
+    0x27CB09, 0xB74F46, 0x3F669E, 0x5FEA2D, 0x7527BA, 0xC7EBE5,
+    0xF17B3D, 0x0739F7, 0x8A5292, 0xEA6BFB, 0x5FB11F, 0x8D5D08,
+    0x560330, 0x46FC7B, 0x6BABF0, 0xCFBC20, 0x9AF436, 0x1DA9E2,
+    0xA9FBBF, 0xCC613C, 0xAF11D1, 0xAFD87E, 0x4D0A30, 0xDBB18C,
+    0x376767, 0xB5DABC, 0xBA1C57, 0x886678, 0x097649, 0x9C2EF6
+];
+
+//pi/2 split into doubles of 24 bits each
+const PI_O2: [f64; 8] = [
+    1.57079625129699707031e+00,
+    7.54978941586159635335e-08,
+    5.39030252995776476554e-15,
+    3.28200341580791294123e-22,
+    1.27065575308067607349e-29,
+    1.22933308981111328932e-36,
+    2.73370053816464559624e-44,
+    2.16741683877804819444e-51
+];
+
+const TWO24: f64 = 1.67772160000000000000e+07;
+const TWON24: f64 = 5.96046447753906250000e-08;
+
+/**
+ * Reduces a large x split into 24 bit chunks by pi/2 using as many bits of 2/pi as
+ * needed, returns the quadrant and writes the remainder into y as a head and a tail.
+ * This is fdlibm's __kernel_rem_pio2 with prec = 2
+ */
+fn kernel_rem_pio2 (x: &[f64], y: &mut [f64; 2], e0: i32) -> i32 {
+    let jk: usize = 4;
+    let jp: usize = jk;
+    let jx: usize = x.len() - 1;
+
+    let jv: i32 = ((e0 - 3) / 24).max(0);
+    let mut q0: i32 = e0 - 24 * (jv + 1);
+
+    let mut f: [f64; 20] = [0.0; 20];
+    let mut q: [f64; 20] = [0.0; 20];
+    let mut fq: [f64; 20] = [0.0; 20];
+    let mut iq: [i32; 20] = [0; 20];
+
+    //Set up f[0] to f[jx + jk] where f[jx + jk] = TWO_OVER_PI[jv + jk]
+    let mut j: i32 = jv - jx as i32;
+    for fi in f.iter_mut().take(jx + jk + 1) {
+        *fi = if j < 0 {0.0} else {TWO_OVER_PI[j as usize] as f64};
+        j += 1;
+    }
+
+    //Compute q[0], q[1] ... q[jk]
+    for i in 0..=jk {
+        let mut fw: f64 = 0.0;
+        for (k, xk) in x.iter().enumerate() {
+            fw += xk * f[jx + i - k];
+        }
+        q[i] = fw;
+    }
+
+    let mut jz: usize = jk;
+    let mut z: f64;
+    let mut n: i32;
+    let mut ih: i32;
+
+    loop {
+        //Distill q[] into iq[] in reverse order
+        z = q[jz];
+        let mut i: usize = 0;
+        let mut j: usize = jz;
+        while j > 0 {
+            let fw: f64 = ((TWON24 * z) as i32) as f64;
+            iq[i] = (z - TWO24 * fw) as i32;
+            z = q[j - 1] + fw;
+            i += 1;
+            j -= 1;
+        }
+
+        //Compute n
+        z = scalbn(z, q0);
+        z -= 8.0 * (z * 0.125).floor();
+        n = z as i32;
+        z -= n as f64;
+        ih = 0;
+        if q0 > 0 {
+            //Need iq[jz - 1] to determine n
+            let i: i32 = iq[jz - 1] >> (24 - q0);
+            n += i;
+            iq[jz - 1] -= i << (24 - q0);
+            ih = iq[jz - 1] >> (23 - q0);
+        } else if q0 == 0 {
+            ih = iq[jz - 1] >> 23;
+        } else if z >= 0.5 {
+            ih = 2;
+        }
+
+        //q > 0.5
+        if ih > 0 {
+            n += 1;
+            let mut carry: i32 = 0;
+            for iqi in iq.iter_mut().take(jz) {
+                let j: i32 = *iqi;
+                if carry == 0 {
+                    if j != 0 {
+                        carry = 1;
+                        *iqi = 0x1000000 - j;
+                    }
+                } else {
+                    *iqi = 0xffffff - j;
+                }
+            }
+
+            //Rare case of chance
+            if q0 == 1 {
+                iq[jz - 1] &= 0x7fffff;
+            } else if q0 == 2 {
+                iq[jz - 1] &= 0x3fffff;
+            }
+
+            if ih == 2 {
+                z = 1.0 - z;
+                if carry != 0 {
+                    z -= scalbn(1.0, q0);
+                }
+            }
+        }
+
+        //Check if recomputation is needed
+        if z == 0.0 {
+            let mut j: i32 = 0;
+            for i in (jk..jz).rev() {
+                j |= iq[i];
+            }
+
+            //Need recomputation
+            if j == 0 {
+                let mut k: usize = 1;
+                while iq[jk - k] == 0 {
+                    k += 1;
+                }
+
+                //Add q[jz + 1] to q[jz + k]
+                for i in jz + 1..=jz + k {
+                    f[jx + i] = TWO_OVER_PI[jv as usize + i] as f64;
+                    let mut fw: f64 = 0.0;
+                    for (k, xk) in x.iter().enumerate() {
+                        fw += xk * f[jx + i - k];
+                    }
+                    q[i] = fw;
+                }
+                jz += k;
+                continue;
+            }
+        }
+
+        break;
+    }
+
+    //Chop off zero terms
+    if z == 0.0 {
+        jz -= 1;
+        q0 -= 24;
+        while iq[jz] == 0 {
+            jz -= 1;
+            q0 -= 24;
+        }
+    } else {
+        //Break z into 24 bit chunks if necessary
+        z = scalbn(z, -q0);
+        if z >= TWO24 {
+            let fw: f64 = ((TWON24 * z) as i32) as f64;
+            iq[jz] = (z - TWO24 * fw) as i32;
+            jz += 1;
+            q0 += 24;
+            iq[jz] = fw as i32;
+        } else {
+            iq[jz] = z as i32;
+        }
+    }
+
+    //Convert integer bit chunks to a floating point value
+    let mut fw: f64 = scalbn(1.0, q0);
+    for i in (0..=jz).rev() {
+        q[i] = fw * iq[i] as f64;
+        fw *= TWON24;
+    }
+
+    //Compute PI_O2[0, ..., jp] * q[jz, ..., 0]
+    for i in (0..=jz).rev() {
+        let mut fw: f64 = 0.0;
+        let mut k: usize = 0;
+        while k <= jp && k <= jz - i {
+            fw += PI_O2[k] * q[i + k];
+            k += 1;
+        }
+        fq[jz - i] = fw;
+    }
+
+    //Compress fq[] into y[]
+    let mut fw: f64 = 0.0;
+    for i in (0..=jz).rev() {
+        fw += fq[i];
+    }
+    y[0] = if ih == 0 {fw} else {-fw};
+    fw = fq[0] - fw;
+    for fqi in fq.iter().take(jz + 1).skip(1) {
+        fw += fqi;
+    }
+    y[1] = if ih == 0 {fw} else {-fw};
+
+    return n & 7;
+}
+
+const INVPIO2: f64 = 6.36619772367581382433e-01;
+const PIO2_1: f64 = 1.57079632673412561417e+00;
+const PIO2_1T: f64 = 6.07710050650619224932e-11;
+const PIO2_2: f64 = 6.07710050630396597660e-11;
+const PIO2_2T: f64 = 2.02226624879595063154e-21;
+const PIO2_3: f64 = 2.02226624871116645580e-21;
+const PIO2_3T: f64 = 8.47842766036889956997e-32;
+
+//High words of n * pi/2 for n = 1 to 32
+const NPIO2_HW: [i32; 32] = [
+    0x3FF921FB, 0x400921FB, 0x4012D97C, 0x401921FB, 0x401F6A7A, 0x4022D97C,
+    0x4025FDBB, 0x402921FB, 0x402C463A, 0x402F6A7A, 0x4031475C, 0x4032D97C,
+    0x40346B9C, 0x4035FDBB, 0x40378FDB, 0x403921FB, 0x403AB41B, 0x403C463A,
+    0x403DD85A, 0x403F6A7A, 0x40407E4C, 0x4041475C, 0x4042106C, 0x4042D97C,
+    0x4043A28C, 0x40446B9C, 0x404534AC, 0x4045FDBB, 0x4046C6CB, 0x40478FDB,
+    0x404858EB, 0x404921FB
+];
+
+/**
+ * Returns x mod pi/2 in y as a head and a tail, and the quadrant.
+ * This is fdlibm's __ieee754_rem_pio2
+ */
+fn rem_pio2 (x: f64, y: &mut [f64; 2]) -> i32 {
+    let hx: i32 = high_word(x);
+    let ix: i32 = hx & 0x7fffffff;
+
+    //|x| ~<= pi/4, no reduction needed
+    if ix <= 0x3fe921fb {
+        y[0] = x;
+        y[1] = 0.0;
+        return 0;
+    }
+
+    //|x| < 3pi/4, special case with n = +-1
+    if ix < 0x4002d97c {
+        if hx > 0 {
+            let mut z: f64 = x - PIO2_1;
+            if ix != 0x3ff921fb {
+                y[0] = z - PIO2_1T;
+                y[1] = (z - y[0]) - PIO2_1T;
+            } else {
+                //Near pi/2, use 33 + 33 + 53 bits of pi
+                z -= PIO2_2;
+                y[0] = z - PIO2_2T;
+                y[1] = (z - y[0]) - PIO2_2T;
+            }
+            return 1;
+        }
+
+        let mut z: f64 = x + PIO2_1;
+        if ix != 0x3ff921fb {
+            y[0] = z + PIO2_1T;
+            y[1] = (z - y[0]) + PIO2_1T;
+        } else {
+            z += PIO2_2;
+            y[0] = z + PIO2_2T;
+            y[1] = (z - y[0]) + PIO2_2T;
+        }
+        return -1;
+    }
+
+    //|x| ~<= 2^19 * (pi/2), medium size
+    if ix <= 0x413921fb {
+        let t: f64 = x.abs();
+        let n: i32 = (t * INVPIO2 + 0.5) as i32;
+        let f_n: f64 = n as f64;
+        let mut r: f64 = t - f_n * PIO2_1;
+
+        //1st round good to 85 bits
+        let mut w: f64 = f_n * PIO2_1T;
+        if n < 32 && ix != NPIO2_HW[(n - 1) as usize] {
+            //Quick check for cancellation
+            y[0] = r - w;
+        } else {
+            let j: i32 = ix >> 20;
+            y[0] = r - w;
+            let i: i32 = j - ((high_word(y[0]) >> 20) & 0x7ff);
+
+            //2nd iteration needed, good to 118 bits
+            if i > 16 {
+                let t: f64 = r;
+                w = f_n * PIO2_2;
+                r = t - w;
+                w = f_n * PIO2_2T - ((t - r) - w);
+                y[0] = r - w;
+                let i: i32 = j - ((high_word(y[0]) >> 20) & 0x7ff);
+
+                //3rd iteration needed, 151 bits accuracy
+                if i > 49 {
+                    let t: f64 = r;
+                    w = f_n * PIO2_3;
+                    r = t - w;
+                    w = f_n * PIO2_3T - ((t - r) - w);
+                    y[0] = r - w;
+                }
+            }
+        }
+        y[1] = (r - y[0]) - w;
+
+        if hx < 0 {
+            y[0] = -y[0];
+            y[1] = -y[1];
+            return -n;
+        }
+        return n;
+    }
+
+    //x is inf or NaN
+    if ix >= 0x7ff00000 {
+        y[0] = x - x;
+        y[1] = y[0];
+        return 0;
+    }
+
+    //Set z = scalbn(|x|, -ilogb(x) + 23) and split it into 24 bit chunks
+    let e0: i32 = (ix >> 20) - 1046;
+    let mut z: f64 = from_words((ix - (e0 << 20)) as u32, low_word(x));
+    let mut tx: [f64; 3] = [0.0; 3];
+    for t in tx.iter_mut().take(2) {
+        *t = (z as i32) as f64;
+        z = (z - *t) * TWO24;
+    }
+    tx[2] = z;
+
+    //Skip zero terms
+    let mut nx: usize = 3;
+    while tx[nx - 1] == 0.0 {
+        nx -= 1;
+    }
+
+    let n: i32 = kernel_rem_pio2(&tx[..nx], y, e0);
+    if hx < 0 {
+        y[0] = -y[0];
+        y[1] = -y[1];
+        return -n;
+    }
+    return n;
+}
+
+/*************************
+  Public functions
+*************************/
+
+/**
+ * Math.sin as the browser computes it
+ */
+pub fn sin (x: f64) -> f64 {
+    let ix: i32 = high_word(x) & 0x7fffffff;
+
+    //|x| ~< pi/4
+    if ix <= 0x3fe921fb {
+        return kernel_sin(x, 0.0, 0);
+    }
+
+    //sin(inf or NaN) is NaN
+    if ix >= 0x7ff00000 {
+        return x - x;
+    }
+
+    let mut y: [f64; 2] = [0.0; 2];
+    match rem_pio2(x, &mut y) & 3 {
+        0 => kernel_sin(y[0], y[1], 1),
+        1 => kernel_cos(y[0], y[1]),
+        2 => -kernel_sin(y[0], y[1], 1),
+        _ => -kernel_cos(y[0], y[1])
+    }
+}
+
+/**
+ * Math.cos as the browser computes it
+ */
+pub fn cos (x: f64) -> f64 {
+    let ix: i32 = high_word(x) & 0x7fffffff;
+
+    //|x| ~< pi/4
+    if ix <= 0x3fe921fb {
+        return kernel_cos(x, 0.0);
+    }
+
+    //cos(inf or NaN) is NaN
+    if ix >= 0x7ff00000 {
+        return x - x;
+    }
+
+    let mut y: [f64; 2] = [0.0; 2];
+    match rem_pio2(x, &mut y) & 3 {
+        0 => kernel_cos(y[0], y[1]),
+        1 => -kernel_sin(y[0], y[1], 1),
+        2 => -kernel_cos(y[0], y[1]),
+        _ => kernel_sin(y[0], y[1], 1)
+    }
+}
+
+/**
+ * parseInt called with a number, which reads the leading integer of the number
+ * written as a string. Returns NaN for NaN and infinities like js does. For numbers
+ * written in exponent form, below 1e-6 or from 1e21 up, that integer is the first
+ * digit, e.g. parse_int(2.5e-7) is 2 and parse_int(-3e25) is -3. Any other number
+ * is truncated towards zero
+ */
+pub fn parse_int (x: f64) -> f64 {
+    if !x.is_finite() {
+        return f64::NAN;
+    }
+
+    //-0 is written as "0", so unlike -0.5 it gives +0
+    if x == 0.0 {
+        return 0.0;
+    }
+
+    let abs: f64 = x.abs();
+    if !(1e-6..1e21).contains(&abs) {
+        //Rust and js both write the shortest digits that round trip, so the first digit matches
+        let first: f64 = format!("{:e}", abs).chars().next().and_then(|c| c.to_digit(10)).unwrap_or(0) as f64;
+        return if x < 0.0 {-first} else {first};
+    }
+
+    return x.trunc();
+}
+
+/**
+ * parse_int converted to an int the way the generator uses it, NaN becomes 0
+ */
+pub fn parse_int_i32 (x: f64) -> i32 {
+    return parse_int(x) as i32;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //x, Math.sin(x) and Math.cos(x) as bits, recorded from node (V8)
+    const SIN_COS: [(u64, u64, u64); 30] = [
+        (0x0000000000000000, 0x0000000000000000, 0x3ff0000000000000), //0
+        (0x8000000000000000, 0x8000000000000000, 0x3ff0000000000000), //-0
+        (0x0000000000000001, 0x0000000000000001, 0x3ff0000000000000), //5e-324
+        (0x81a56e1fc2f8f359, 0x81a56e1fc2f8f359, 0x3ff0000000000000), //-1e-300
+        (0x3e112e0be826d695, 0x3e112e0be826d695, 0x3ff0000000000000), //1e-9
+        (0x3fe0000000000000, 0x3fdeaee8744b05f0, 0x3fec1528065b7d50), //0.5
+        (0x3fe921fb54442d18, 0x3fe6a09e667f3bcc, 0x3fe6a09e667f3bcd), //pi/4
+        (0x3fe921fb54442d19, 0x3fe6a09e667f3bcd, 0x3fe6a09e667f3bcc), //just above pi/4
+        (0x3ff0000000000000, 0x3feaed548f090cee, 0x3fe14a280fb5068c), //1
+        (0xc002d97c7f3321d2, 0xbfe6a09e667f3bcd, 0xbfe6a09e667f3bcc), //-3pi/4
+        (0x400921fb54442d18, 0x3ca1a62633145c07, 0xbff0000000000000), //pi
+        (0x4024000000000000, 0xbfe1689ef5f34f52, 0xbfead9ac890c6b1f), //10
+        (0x4059000000000000, 0xbfe03425b78c4db8, 0x3feb981dbf665fdf), //100
+        (0x413921fb54442d18, 0xbdd1a62633145c07, 0x3ff0000000000000), //2^20 * pi/2, the last medium argument
+        (0x413921fc00000000, 0x3fe3e479a61836eb, 0x3fe910d18d26c0d4), //1647100, the first large argument
+        (0x41d0000000000000, 0xbfe3c12353728caf, 0x3fe92cb46a920144), //2^30
+        (0x4480f0cf064dd592, 0xbfeb453ab76bf397, 0x3fe0be2cef01c8f4), //1e22
+        (0xd4b249ad2594c37d, 0x3fd85c5e5b929359, 0x3fed9757496841f5), //-1e100
+        (0x7e37e43c8800759c, 0xbfea2c16b010e385, 0xbfe2699022adc4c1), //1e300
+        (0x7fefffffffffffff, 0x3f7452fc98b34e97, 0xbfefffe62ecfab75), //largest double
+        //Inputs where the platform's f64::sin or f64::cos round differently
+        (0xbfe764d1c5082c02, 0xbfe55d721df0b56d, 0x3fe7d2b0cad905f2), //-0.7310570572166173
+        (0x4000823539fe7855, 0x3fec315170f0f12e, 0xbfde46eb0b713951), //2.063578084066686
+        (0xc01b126a95ef4f82, 0xbfddd3733d288cc2, 0x3fec5005eba09420), //-6.767984717112883
+        (0x4039b6a70fa4b4f0, 0x3fe18e84c93ad3f4, 0x3feac0f42078e83f), //25.713486650191896
+        (0xc052aa4c488fb720, 0x3fe5838527f60084, 0x3fe7b054379820ce), //-74.66090597185803
+        (0x40aa37f74979788c, 0x3fe617ad603f6cbe, 0x3fe7266593a852c5), //3355.9829824409153
+        (0x40f501de00000000, 0xbfe6d1250213b332, 0xbfe66faed7e44d2e), //86045.875
+        (0x417c675488400dd7, 0x3fecda06d5d8c208, 0xbfdbaebda1227439), //29783368.5156382
+        (0xc1dec23511b506ff, 0xbfd163ef28df932a, 0xbfeecbc6d33722fc), //-2064176198.828552
+        (0xc3d7c85358686001, 0xbfe9091949696868, 0xbfe3ee2ffd5c115e)  //-6.854845189551293e18
+    ];
+
+    //x and parseInt(x) as bits, recorded from node (V8)
+    const PARSE_INT: [(u64, u64); 19] = [
+        (0x3e7ad7f29abcaf48, 0x3ff0000000000000), //1e-7 is written "1e-7", 1
+        (0x3eb0c6f7a0b5ed8d, 0x0000000000000000), //1e-6 is written "0.000001", 0
+        (0x3eb0c2ac1dbbe3d8, 0x4022000000000000), //9.99e-7, 9
+        (0x3eb92a737110e454, 0x0000000000000000), //1.5e-6, 0
+        (0x444b1ae4d6e2ef50, 0x3ff0000000000000), //1e21 is written "1e+21", 1
+        (0x444b1ae4d6e2ef4f, 0x444b1ae4d6e2ef4f), //the double below 1e21 is written in full
+        (0x4454542ba12a337c, 0x3ff0000000000000), //1.5e21, 1
+        (0xbe7ad7f29abcaf48, 0xbff0000000000000), //-1e-7, -1
+        (0xbeb0c6f7a0b5ed8d, 0x8000000000000000), //-1e-6, -0
+        (0xbfe0000000000000, 0x8000000000000000), //-0.5, -0
+        (0x8000000000000000, 0x0000000000000000), //-0, +0
+        (0x0000000000000000, 0x0000000000000000), //0
+        (0x405ef9999999999a, 0x405ec00000000000), //123.9, 123
+        (0xc05ef9999999999a, 0xc05ec00000000000), //-123.9, -123
+        (0x3e90c6f7a0b5ed8d, 0x4000000000000000), //2.5e-7, 2
+        (0xc538d0bf423c03d9, 0xc008000000000000), //-3e25, -3
+        (0x0000000000000001, 0x4014000000000000), //5e-324, 5
+        (0x4340000000000001, 0x4340000000000001), //2^53 + 2
+        (0x3fefffffca501acb, 0x0000000000000000)  //0.9999999, 0
+    ];
+
+    #[test]
+    fn sin_and_cos_match_v8_bit_for_bit () {
+        for (x, sin_bits, cos_bits) in SIN_COS {
+            let x: f64 = f64::from_bits(x);
+            assert_eq!(sin(x).to_bits(), sin_bits, "sin({:e})", x);
+            assert_eq!(cos(x).to_bits(), cos_bits, "cos({:e})", x);
+        }
+    }
+
+    #[test]
+    fn sin_and_cos_of_nan_and_infinity_are_nan () {
+        for x in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            assert!(sin(x).is_nan());
+            assert!(cos(x).is_nan());
+        }
+    }
+
+    #[test]
+    fn parse_int_matches_v8_bit_for_bit () {
+        for (x, bits) in PARSE_INT {
+            let x: f64 = f64::from_bits(x);
+            assert_eq!(parse_int(x).to_bits(), bits, "parseInt({:e})", x);
+        }
+    }
+
+    #[test]
+    fn parse_int_of_nan_and_infinity_is_nan () {
+        for x in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            assert!(parse_int(x).is_nan());
+            assert_eq!(parse_int_i32(x), 0);
+        }
+        assert_eq!(parse_int_i32(-1e-7), -1);
+        assert_eq!(parse_int_i32(63.99), 63);
+    }
+}
@@ -1,38 +1,147 @@
+#![allow(clippy::needless_return)]
+
+mod block;
+mod block_pos;
+mod column_index;
+mod conformance;
+mod diff;
+mod error;
+mod generator;
+pub mod jsmath;
+mod pipeline;
+mod preview;
 mod random_level_worker;
 mod random;
-
-use rusqlite::{Connection, Result};
+mod schema;
+mod seed_crack;
+mod seed_search;
+mod settings;
+mod spawn;
+mod tile_map;
+mod trace;
+mod validate;
+mod world_generator;
+mod writer;
+
+pub use block::Block;
+pub use block_pos::BlockPos;
+pub use column_index::{ColumnIndex, ColumnInfo};
+pub use conformance::{layer_hashes, read_tile_map_dump, ConformanceReport, Fixture, Mismatch, MismatchKind};
+pub use diff::{diff_tile_maps, ChangePolicy};
+pub use error::{Error, Result};
+pub use generator::{CancelToken, ClassicGenerator, Stage};
+pub use pipeline::{classic_stages, ClassicStage, GenerationStage};
+pub use preview::{ApproximatePreview, SurfaceKind};
+pub use random::Random;
+pub use random_level_worker::RandomLevel;
+pub use schema::{load_saved_game, SavedGameInfo, SchemaVersion};
+pub use seed_crack::{CrackResult, Observation, SeedClass, SeedCracker};
+pub use seed_search::{block_count_at_least, lake_near_centre, spawn_on_island, SearchCheckpoint, SearchOutcome, SeedCandidate, SeedMatch, SeedPredicate, SeedSearch};
+pub use settings::GenerationSettings;
+pub use spawn::{find_spawn, find_spawn_with, highest_tile, NO_SPAWN_Y};
+pub use tile_map::{Column, TileMap};
+pub use trace::{FloodFill, GenerationTrace, OreVein, Tree, VeinStep};
+pub use validate::{deserialize_saved_game_lenient, ValidationIssue};
+pub use world_generator::{FlatGenerator, IslandGenerator, VoidGenerator, WorldGenerator};
+pub use writer::{write_saved_game_json, write_saved_game_json_with};
+
+use rusqlite::Connection;
+
+use indexmap::IndexMap;
 
 use serde::{Deserialize, Serialize};
-use serde_json;
 
-use snap;
 use snap::raw::{Decoder, Encoder};
 
-use std::collections::HashMap;
 use std::fs;
 
+//Height of every classic js world, only the width and length vary
+pub const WORLD_HEIGHT: i32 = 64;
+
+//Map of changed blocks keyed by position, entries keep the order they appear in the save
+pub type ChangedBlockMap = IndexMap<BlockPos, ChangedBlocks>;
+
 /**
  * JSLevel struct stores the object format of a
  * classic js level of type:
  * {"worldSeed":0,"changedBlocks":{},"worldSize":128,"version":1}
  * References the ChangedBlocks struct
  * worldSeed: This is the seed of the world
- * changedBlocks: This is an array of all changedBlocks in the world
+ * changedBlocks: This is a map of all changedBlocks in the world, keyed by position
  * worldSize: This is the width/length of the world, must be 128, 256, or 512
- * version: Yeah, I have no clue what this is, but it's seemingly always 1 so...
+ * version: Version of the save layout, always 1 for the official game, see SchemaVersion
+ * extra: Any other top level keys, such as ones added by newer builds or forks. These
+ * are kept in the order they were read and written back after version
  */
-#[derive(Serialize, Deserialize)]
+#[allow(non_snake_case)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct JSLevel {
     pub worldSeed: i64,
-    pub changedBlocks: HashMap<String,ChangedBlocks>,
+    pub changedBlocks: ChangedBlockMap,
     pub worldSize: i32,
-    pub version: u8
+    pub version: u8,
+    #[serde(flatten)]
+    pub extra: IndexMap<String, serde_json::Value>
 }
 
 impl JSLevel {
-    pub fn new (worldSeed: i64, changedBlocks: HashMap<String,ChangedBlocks>, worldSize: i32, version: u8) -> Self {
-        JSLevel { worldSeed, changedBlocks, worldSize, version } 
+    #[allow(non_snake_case)]
+    pub fn new (worldSeed: i64, changedBlocks: ChangedBlockMap, worldSize: i32, version: u8) -> Self {
+        JSLevel { worldSeed, changedBlocks, worldSize, version, extra: IndexMap::new() } 
+    }
+
+    /**
+     * Creates a level for an edited tile map, where changedBlocks holds
+     * only the blocks that differ from the natural generation of the seed
+     */
+    pub fn from_tile_map (seed: i64, world_size: i32, tile_map: &TileMap) -> Result<Self> {
+        check_world_size(world_size)?;
+        return JSLevel::from_tile_map_with(seed, world_size, tile_map, &ClassicGenerator::new(seed));
+    }
+
+    /**
+     * Same as from_tile_map, but the changed blocks are the ones that differ
+     * from what generator makes for the seed instead of natural generation
+     */
+    pub fn from_tile_map_with (seed: i64, world_size: i32, tile_map: &TileMap, generator: &dyn WorldGenerator) -> Result<Self> {
+        let natural: TileMap = generator.generate_for_size(world_size, seed)?;
+        let changed_blocks: ChangedBlockMap = diff_tile_maps(&natural, tile_map, &ChangedBlockMap::new(), ChangePolicy::Minimal)?;
+        return Ok(JSLevel::new(seed, changed_blocks, world_size, SchemaVersion::LATEST.version()));
+    }
+
+    /**
+     * Builds the world a player sees after loading this save. Like the game,
+     * the terrain is regenerated from worldSeed and worldSize and then every
+     * changed block is placed over it. The game places bt whatever the value
+     * of a is, as a only records whether bt differs from natural generation.
+     * Changed blocks outside of the world are ignored, the same way the game
+     * ignores them
+     */
+    pub fn to_tile_map (&self) -> Result<TileMap> {
+        check_world_size(self.worldSize)?;
+        return self.to_tile_map_with(&ClassicGenerator::new(self.worldSeed));
+    }
+
+    /**
+     * Same as to_tile_map, but the terrain comes from generator, for
+     * levels that were saved against a custom map
+     */
+    pub fn to_tile_map_with (&self, generator: &dyn WorldGenerator) -> Result<TileMap> {
+        let mut tile_map: TileMap = generator.generate_for_size(self.worldSize, self.worldSeed)?;
+        self.apply_changed_blocks(&mut tile_map);
+        return Ok(tile_map);
+    }
+
+    /**
+     * Places every changed block of this level onto an existing tile map.
+     * Changed blocks outside of the tile map are skipped, returns how many were
+     */
+    pub fn apply_changed_blocks (&self, tile_map: &mut TileMap) -> usize {
+        let mut skipped: usize = 0;
+        for (pos, changed) in &self.changedBlocks {
+            if !tile_map.set(pos.x, pos.y, pos.z, changed.bt) { skipped += 1; }
+        }
+        return skipped;
     }
 }
 
@@ -43,11 +152,14 @@ impl JSLevel {
  * of each changed block in the world:
  * p0_0_0: position of block in world, where px_y_z
  * a: 0 if block does match natural generation / 1 if block does not match natural generation
- * bt: type of block
+ * bt: the block type, written as its id. An id outside of the palette fails to
+ * deserialize, deserialize_saved_game_lenient reports it as UnknownBlock instead
  */
-#[derive(Serialize, Deserialize)]
-pub struct ChangedBlocks {pub a: u8, pub bt: u8}
-impl ChangedBlocks { pub fn new (a: u8, bt: u8) -> Self {ChangedBlocks { a, bt }}}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChangedBlocks {pub a: u8, pub bt: Block}
+impl ChangedBlocks {
+    pub fn new (a: u8, bt: Block) -> Self {ChangedBlocks { a, bt }}
+}
 
 /**
  * LocalStorage struct stores input from localStorage db files
@@ -57,6 +169,7 @@ impl ChangedBlocks { pub fn new (a: u8, bt: u8) -> Self {ChangedBlocks { a, bt }
  * compression_type: 1
  * value: The actual savedGame, so the actual world
  */
+#[allow(dead_code)]
 pub struct LocalStorage {
     key: String,
     utf16_length: i32,
@@ -69,102 +182,89 @@ pub struct LocalStorage {
 /**
  * Following function accepts a world size and seed,
  * and then passes them to the js world generation 
- * functionality, and then returns the output as a Vec<>
+ * functionality, and then returns the output as a TileMap.
+ * Only the sizes the game offers are accepted, use
+ * ClassicGenerator for any other dimensions
  */
-pub fn get_tile_map (world_size: i32, seed: i64) -> Vec<u8> {
-    let y: i32 = 64;
-    let level: HashMap<usize, u8> = random_level_worker::start_generation(world_size, seed); //Generating hashmap of all tiles in the world
-    let mut tile_map: Vec<u8> = Vec::new();
+pub fn generate_tile_map (world_size: i32, seed: i64) -> Result<TileMap> {
+    check_world_size(world_size)?;
+    return ClassicGenerator::new(seed).size(world_size).generate();
+}
 
-    for i in 0..world_size * y * world_size {
-        tile_map.push(level.get(&(i as usize)).copied().unwrap_or(0)); //Copying hashmap to vec
-    }
+/**
+ * Same as generate_tile_map, but calls progress with the current Stage and a
+ * percentage from 0 to 100 while the world generates, so a slow 512 world
+ * can show a loading screen like the game does
+ */
+pub fn generate_with_progress<F: FnMut(Stage, u8)> (world_size: i32, seed: i64, progress: F) -> Result<TileMap> {
+    check_world_size(world_size)?;
+    return ClassicGenerator::new(seed).size(world_size).generate_with_progress(progress);
+}
+
+/**
+ * Returns an approximate preview of a world that samples every step-th column,
+ * see ClassicGenerator::preview. Much faster than generate_tile_map, but it is
+ * only a sketch of the world
+ */
+pub fn preview_world (world_size: i32, seed: i64, step: i32) -> Result<ApproximatePreview> {
+    check_world_size(world_size)?;
+    return ClassicGenerator::new(seed).size(world_size).preview(step);
+}
 
-    return tile_map
+/**
+ * Same as generate_tile_map, but returns the tiles in the flat
+ * layout described on TileMap: X fastest, then Z, then Y
+ */
+pub fn get_tile_map (world_size: i32, seed: i64) -> Result<Vec<u8>> {
+    return Ok(generate_tile_map(world_size, seed)?.to_flat());
+}
+
+/**
+ * Checks that a world size is one that classic js can generate
+ */
+fn check_world_size (world_size: i32) -> Result<()> {
+    match world_size {
+        128 | 256 | 512 => Ok(()),
+        _ => Err(Error::InvalidWorldSize(world_size))
+    }
 }
 
 /**
  * Converts a json string in the savedGame format into
  * a JSLevel struct
  */
-pub fn deserialize_saved_game (json_string: String) -> JSLevel {
-    let level: JSLevel = serde_json::from_str(&json_string).unwrap();
-    return level;
+pub fn deserialize_saved_game (json_string: String) -> Result<JSLevel> {
+    let level: JSLevel = serde_json::from_str(&json_string)?;
+    return Ok(level);
 }
 
 /**
- * Following function accepts a level in the JS form, a tile_map, and optimization and
- * writes it into the classic javascript object format
+ * Following function accepts a level in the JS form, a tile_map, and a change policy and
+ * writes it into the classic javascript object format. Entries already in the level's
+ * changedBlocks are placed over the tile map before it is compared against natural
+ * generation, see ChangePolicy for which blocks end up in the output. To write straight
+ * into a file or socket instead of a String, use write_saved_game_json
  */
-pub fn serialize_saved_game (level: JSLevel, tile_map: Vec<u8>, opt: u8) -> String {
-
-    //Assigning x, y, and z of world
-    let x: i32 = level.worldSize;
-    let y: i32 = 64;
-    let z: i32 = level.worldSize;
-    let tile_map1 = get_tile_map(level.worldSize, level.worldSeed);
-
-    let mut output: String = String::from("{"); //Opening json object
-
-    output += &format!(r#""worldSeed":{},"#,level.worldSeed.to_string()); //Adding seed key value pair
-
-    //Adding changed blocks key value pair
-    output += r#""changedBlocks":"#; //Adding blocks key
-    output += "{"; //Opening block values object
-
-    //Variables for the tiles and a value
-    let mut t: u8;
-    let mut t1: u8;
-    let mut a: u8; //a = 0 if changed block matches generation, a = 1 if changed block does not match generation
-
-    //Iterating through all blocks
-    //Tilemaps are stored in X,Z,Y format, where [0] is X:0, Y:0, Z:0 & [1] is X:1, Y:0, Z:0 etc.
-    let mut flag: bool = false;
-    for i in 0..y {
-        for j in 0..z {
-            for k in 0..x {
-
-                /* Following code block will be more useful once a changed blocks hashmap is implemented */
-
-                //Setting tile for changed block and checking whether it matches tile generated by seed
-                let mut flag1 = false;
-                let key: String = String::from(format!(r#""p{}_{}_{}":"#,k,i,j));
-                let bt: u8 = level.changedBlocks.get(&key).unwrap_or(&ChangedBlocks::new(1,255)).bt;
-                t = tile_map[((i*z*x) + (j*x) + k) as usize];
-                t1 = tile_map1[((i*z*x) + (j*x) + k) as usize];
-                if bt != 255 { t = bt }
-                if t == t1 { a = 0 } else { a = 1 } //a = 0 if changed block matches generation, a = 1 if changed block does not match generation
-
-                //If opt == 2 the tile must differ from natural generation to write to array
-                //If opt == 1 either the tile differs from natural generation or it is already considered a changed block to write to array
-                //If opt == 0 tile is written to array
-                //Default value should be 1 or 2, opt 0 is storage intensive and causes unnecessary lag
-                if (opt == 2 && a == 1) || (opt == 1 && (bt != 255 || a == 1)) || opt == 0 { flag1 = true }
-                
-                if flag1 {
-                    //Creating key for changed block
-                    output += &key;
-
-                    //Creating value for changed block
-                    output += "{";
-                    output += &format!(r#""a":{},"bt":{}"#,a,t);
-                    output += "},";
-
-                    flag = true;
-                }
-
-            }
-        }
-    }
+pub fn serialize_saved_game (level: JSLevel, tile_map: Vec<u8>, policy: ChangePolicy) -> Result<String> {
 
-    if flag {output.pop();} //Removing extra comma
-    output += "},"; //Closing Changed Blocks object
+    check_world_size(level.worldSize)?;
+    let generator: ClassicGenerator = ClassicGenerator::new(level.worldSeed);
+    return serialize_saved_game_with(level, tile_map, policy, &generator);
 
-    output += &format!{r#""worldSize":{},"#,level.worldSize}; //Adding world size key value pair
-    output += &format!{r#""version":{}"#,level.version}; //Adding version key value pair
+}
+
+/**
+ * Same as serialize_saved_game, but blocks are compared against what
+ * generator makes for the level's seed instead of natural generation
+ */
+pub fn serialize_saved_game_with (level: JSLevel, tile_map: Vec<u8>, policy: ChangePolicy, generator: &dyn WorldGenerator) -> Result<String> {
 
-    output += "}"; //Closing json object
-    return output;
+    let edited: TileMap = TileMap::from_flat(level.worldSize, WORLD_HEIGHT, level.worldSize, &tile_map)?;
+
+    let mut output: Vec<u8> = Vec::new();
+    write_saved_game_json_with(&mut output, &level, &edited, policy, generator)?;
+
+    return Ok(String::from_utf8(output).expect("serde_json only ever writes valid utf8"));
 
 }
 
@@ -173,13 +273,13 @@ pub fn serialize_saved_game (level: JSLevel, tile_map: Vec<u8>, opt: u8) -> Stri
  * and then compares it agains the given tilemap to create a json formatted
  * JS world save
  */
-pub fn serialize_saved_game_from_seed (seed: i64, tile_map: Vec<u8>) -> String {
+pub fn serialize_saved_game_from_seed (seed: i64, tile_map: Vec<u8>) -> Result<String> {
 
-    let world_size: i32 = ((tile_map.len()/64) as f64).sqrt() as i32;
-    let changed_blocks: HashMap<String, ChangedBlocks> = HashMap::new();
-    let level = JSLevel::new(seed, changed_blocks, world_size, 1);
+    let world_size: i32 = ((tile_map.len()/WORLD_HEIGHT as usize) as f64).sqrt() as i32;
+    let changed_blocks: ChangedBlockMap = ChangedBlockMap::new();
+    let level = JSLevel::new(seed, changed_blocks, world_size, SchemaVersion::LATEST.version());
 
-    return serialize_saved_game(level, tile_map, 2);
+    return serialize_saved_game(level, tile_map, ChangePolicy::Minimal);
 }
 
 /**
@@ -207,26 +307,23 @@ pub fn read_saved_game (file_path: String) -> Result<String> {
         }
     ))?;
 
-    //Retreiving the compressed save game object and length
-    let mut compressed_saved_game: Vec<u8> = Vec::new();
-    let mut decompressed_length: i32 = 0;
+    //Retreiving the save game object
+    let mut saved_game: Option<LocalStorage> = None;
     for entry in entries {
-        let local: LocalStorage = entry.unwrap();
+        let local: LocalStorage = entry?;
         if local.key == "savedGame" {
-            compressed_saved_game = local.value;
-            decompressed_length = local.utf16_length;
+            saved_game = Some(local);
             break;
         }
     }
+    let saved_game: LocalStorage = saved_game.ok_or(Error::MissingSavedGame)?;
 
-    //Creating an array with the correct length for storing the decompressed bytes
-    let mut decompressed: Vec<u8> = Vec::new();
-    for _ in 0..decompressed_length {
-        decompressed.push(0);
-    }
-
-    //Decompressing using snappy compression
-    Decoder::decompress(&mut Decoder::new(), &compressed_saved_game, &mut decompressed).unwrap();
+    //Decompressing using snappy compression, compression_type 0 means the value is stored as is
+    let decompressed: Vec<u8> = if saved_game.compression_type == 0 {
+        saved_game.value
+    } else {
+        Decoder::new().decompress_vec(&saved_game.value)?
+    };
 
     //Converting the character codes to characters
     let mut characters: Vec<char> = Vec::new();
@@ -246,6 +343,8 @@ pub fn read_saved_game (file_path: String) -> Result<String> {
  * to the db and saved. Note this only applies to Firefox,
  * as firefox is the only browser that I know of that uses
  * this structure. Chromium support in the future...
+ * Every character is stored as one byte, so a string with a
+ * character above U+00FF returns Error::UnsupportedCharacter
  */
 pub fn write_saved_game (file_path: String, json_string: String) -> Result<()> {
 
@@ -253,26 +352,14 @@ pub fn write_saved_game (file_path: String, json_string: String) -> Result<()> {
     let characters: Vec<char> = json_string.chars().collect();
     let utf16_length: i32  = characters.len() as i32;
 
-    //Converting chars to u8
+    //Converting chars to u8, every char has to fit in a single byte
     let mut decompressed: Vec<u8> = Vec::new();
     for ch in characters {
-        decompressed.push(ch as u8);
+        decompressed.push(u8::try_from(ch).map_err(|_| Error::UnsupportedCharacter(ch))?);
     }
 
-    //Creating the output array
-    let max_comp_length = snap::raw::max_compress_len(decompressed.len());
-    let mut compressed: Vec<u8> = Vec::new();
-    for _ in 0..max_comp_length {
-        compressed.push(0);
-    }
-
-    //Compressing and cleaning the compressed value
-    Encoder::compress(&mut Encoder::new(), &decompressed, & mut compressed).unwrap();
-    let mut b: u8 = 0;
-    while b == 0 {
-        b = compressed.pop().unwrap();
-    }
-    compressed.push(b);
+    //Compressing the value
+    let compressed: Vec<u8> = Encoder::new().compress_vec(&decompressed)?;
 
     let conn: Connection = Connection::open(file_path)?;
 
@@ -291,7 +378,7 @@ pub fn write_saved_game (file_path: String, json_string: String) -> Result<()> {
     //Inserting the savedGame into the database
     let mut stmt = conn.prepare("INSERT OR REPLACE INTO data (key, utf16_length, conversion_type, compression_type, value) values (?1, ?2, ?3, ?4, ?5)" )?;
 
-    stmt.execute(("savedGame", utf16_length, 1, 1, compressed));
+    stmt.execute(("savedGame", utf16_length, 1, 1, compressed))?;
 
     Ok(())
 
@@ -303,14 +390,70 @@ pub fn write_saved_game (file_path: String, json_string: String) -> Result<()> {
  * savedGame, in order for it to be copy pasted into a browser console to 
  * insert the world save
  */
-pub fn write_saved_game_command (file: String, json_string: String) -> String {
+pub fn write_saved_game_command (file: String, json_string: String) -> Result<String> {
     let open: String = String::from(r#"localStorage.setItem("savedGame", `"#); //Opening command for localStorage
     let close: String = String::from(r#"`)"#); //Closing command for localStorage
 
-    let output: String = String::from(format!{r"{open}{json_string}{close}"});
+    let output: String = format!{r"{open}{json_string}{close}"};
+
+    if !file.is_empty() {fs::write(file, output.clone())?} //Attempting to write localStorage command to file
+
+    return Ok(output);
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn to_tile_map_places_changed_blocks_in_the_world () {
+        let flat: FlatGenerator = FlatGenerator::default();
+        let mut changed_blocks: ChangedBlockMap = ChangedBlockMap::new();
+        changed_blocks.insert(BlockPos::new(0, 31, 0), ChangedBlocks::new(1, Block::Glass));
+        changed_blocks.insert(BlockPos::new(5, 0, 7), ChangedBlocks::new(0, Block::Air));
+        changed_blocks.insert(BlockPos::new(128, 0, 0), ChangedBlocks::new(1, Block::Tnt));
+        changed_blocks.insert(BlockPos::new(0, -1, 0), ChangedBlocks::new(1, Block::Tnt));
+        changed_blocks.insert(BlockPos::new(0, WORLD_HEIGHT, 0), ChangedBlocks::new(1, Block::Tnt));
+        let level: JSLevel = JSLevel::new(1, changed_blocks, 128, 1);
+
+        let natural: TileMap = flat.generate_for_size(128, 1).unwrap();
+        let world: TileMap = level.to_tile_map_with(&flat).unwrap();
+        assert_eq!(world.get(0, 31, 0), Some(Block::Glass));
+        assert_eq!(world.get(5, 0, 7), Some(Block::Air));
+        assert!(!world.blocks().contains(&Block::Tnt));
+        assert_eq!(world.positions().zip(natural.blocks()).filter(|((_, _, _, block), natural)| block != *natural).count(), 2);
+
+        let mut applied: TileMap = natural.clone();
+        assert_eq!(level.apply_changed_blocks(&mut applied), 3);
+        assert_eq!(applied, world);
+    }
+
+    #[test]
+    fn to_tile_map_checks_the_world_size () {
+        let level: JSLevel = JSLevel::new(1, ChangedBlockMap::new(), 100, 1);
+        assert!(matches!(level.to_tile_map(), Err(Error::InvalidWorldSize(100))));
+    }
+
+    #[test]
+    fn write_saved_game_round_trips_latin1 () {
+        let path: std::path::PathBuf = std::env::temp_dir().join(format!("mc-classic-js-latin1-{}.sqlite", std::process::id()));
+        let json: String = String::from("{\"name\":\"caf\u{e9}\"}");
 
-    if file != "" {fs::write(file, output.clone()).expect("Error when writing to file")} //Attempting to write localStorage command to file
+        write_saved_game(path.display().to_string(), json.clone()).unwrap();
+        let read: Result<String> = read_saved_game(path.display().to_string());
+        let _ = fs::remove_file(&path);
 
-    return output;
+        assert_eq!(read.unwrap(), json);
+    }
 
+    #[test]
+    fn write_saved_game_rejects_wide_characters () {
+        let path: std::path::PathBuf = std::env::temp_dir().join(format!("mc-classic-js-wide-{}.sqlite", std::process::id()));
+
+        let result: Result<()> = write_saved_game(path.display().to_string(), String::from("{\"name\":\"\u{263a}\"}"));
+
+        assert!(matches!(result, Err(Error::UnsupportedCharacter('\u{263a}'))));
+        assert!(!path.exists());
+    }
 }
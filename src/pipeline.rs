@@ -0,0 +1,131 @@
+use crate::generator::Stage;
+use crate::random_level_worker::RandomLevel;
+
+use std::fmt;
+use std::sync::Arc;
+
+/**
+ * GenerationStage trait is one step of the classic generator. ClassicGenerator runs a
+ * list of them in order over the same RandomLevel, by default the eight stages of the
+ * game as ClassicStage. Implement it to add a step, such as dungeons after Carving,
+ * or to replace one, such as Planting with different trees, see ClassicGenerator::stage_after
+ * and ClassicGenerator::replace_stage
+ */
+pub trait GenerationStage: Send + Sync {
+
+    /**
+     * The stage progress is reported under while this one runs. A custom
+     * stage reports as the classic stage it belongs with
+     */
+    fn stage (&self) -> Stage;
+
+    /**
+     * Runs the stage over the level
+     */
+    fn run (&self, level: &mut RandomLevel<'_>);
+}
+
+impl fmt::Debug for dyn GenerationStage {
+    fn fmt (&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "GenerationStage({:?})", self.stage())
+    }
+}
+
+/**
+ * ClassicStage struct runs one of the game's own stages,
+ * exactly as the game does
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ClassicStage(pub Stage);
+
+impl GenerationStage for ClassicStage {
+    fn stage (&self) -> Stage {
+        return self.0;
+    }
+
+    fn run (&self, level: &mut RandomLevel<'_>) {
+        match self.0 {
+            Stage::Raising => level.raise(),
+            Stage::Eroding => level.erode(),
+            Stage::Soiling => level.soil(),
+            Stage::Carving => level.carve(),
+            Stage::Watering => level.water(),
+            Stage::Melting => level.melt(),
+            Stage::Growing | Stage::Planting => {
+                //Both read the heights while they change the tiles
+                let heights: Vec<f64> = std::mem::take(level.heights_mut());
+                if self.0 == Stage::Growing { level.grow(&heights); } else { level.plant(&heights); }
+                *level.heights_mut() = heights;
+            }
+        }
+    }
+}
+
+/**
+ * Returns the stages of the game in the order it runs them,
+ * the list ClassicGenerator starts with
+ */
+pub fn classic_stages () -> Vec<Arc<dyn GenerationStage>> {
+    return Stage::ALL.iter().map(|stage| Arc::new(ClassicStage(*stage)) as Arc<dyn GenerationStage>).collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::Block;
+    use crate::generator::ClassicGenerator;
+    use crate::tile_map::TileMap;
+
+    use std::sync::Mutex;
+
+    //Keeps a copy of the tiles as they are when it runs
+    struct Snapshot(Arc<Mutex<Vec<u8>>>);
+
+    impl GenerationStage for Snapshot {
+        fn stage (&self) -> Stage { Stage::Carving }
+
+        fn run (&self, level: &mut RandomLevel<'_>) {
+            *self.0.lock().unwrap() = level.tiles().to_vec();
+        }
+    }
+
+    struct Nothing;
+
+    impl GenerationStage for Nothing {
+        fn stage (&self) -> Stage { Stage::Planting }
+
+        fn run (&self, _: &mut RandomLevel<'_>) {}
+    }
+
+    #[test]
+    fn stage_after_runs_right_after_the_stage () {
+        let tiles: Arc<Mutex<Vec<u8>>> = Arc::new(Mutex::new(Vec::new()));
+        let generator: ClassicGenerator = ClassicGenerator::new(7).size(128).stage_after(Stage::Carving, Snapshot(tiles.clone()));
+
+        let order: Vec<Stage> = generator.generation_stages().iter().map(|s| s.stage()).collect();
+        assert_eq!(order[3..6], [Stage::Carving, Stage::Carving, Stage::Watering]);
+
+        //A stage that only looks leaves the world as it was
+        assert_eq!(generator.generate().unwrap(), ClassicGenerator::new(7).size(128).generate().unwrap());
+
+        let carved: TileMap = ClassicGenerator::new(7).size(128).stages(classic_stages()[..4].to_vec()).generate().unwrap();
+        assert_eq!(*tiles.lock().unwrap(), carved.to_flat());
+    }
+
+    #[test]
+    fn replaced_planting_plants_no_trees () {
+        let generator: ClassicGenerator = ClassicGenerator::new(7).size(128).replace_stage(Stage::Planting, Nothing);
+        assert_eq!(generator.generation_stages().len(), 8);
+        assert!(!generator.is_classic());
+
+        let world: TileMap = generator.generate().unwrap();
+        assert!(!world.blocks().contains(&Block::Log));
+        assert!(!world.blocks().contains(&Block::Leaves));
+    }
+
+    #[test]
+    fn classic_stages_are_the_default_generator () {
+        let staged: TileMap = ClassicGenerator::new(7).size(128).stages(classic_stages()).generate().unwrap();
+        assert_eq!(staged.to_flat(), ClassicGenerator::new(7).size(128).generate().unwrap().to_flat());
+    }
+}
@@ -0,0 +1,234 @@
+use crate::block::Block;
+
+/**
+ * SurfaceKind enum is what the top of a column of an ApproximatePreview looks like
+ * Grass: land above the sea
+ * Sand: beaches, land just at the sea level where the sand noise is high
+ * Water: the column is under the sea, see ApproximatePreview::water_depth
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SurfaceKind {
+    Grass,
+    Sand,
+    Water
+}
+
+impl SurfaceKind {
+
+    /**
+     * Returns the block the classic generator would most likely put on top of the column
+     */
+    pub fn block (self) -> Block {
+        match self {
+            SurfaceKind::Grass => Block::Grass,
+            SurfaceKind::Sand => Block::Sand,
+            SurfaceKind::Water => Block::Water
+        }
+    }
+
+    /**
+     * Returns a colour for thumbnails, as red, green and blue
+     */
+    pub fn colour (self) -> [u8; 3] {
+        match self {
+            SurfaceKind::Grass => [89, 150, 56],
+            SurfaceKind::Sand => [219, 206, 153],
+            SurfaceKind::Water => [48, 84, 196]
+        }
+    }
+}
+
+/**
+ * ApproximatePreview struct is a quick sketch of a world, see ClassicGenerator::preview.
+ * Only Raising and Eroding run for real, for every step-th column along X and Z, and the rest
+ * is estimated from the noise of Soiling and Growing without any tiles. A step of 4 takes a
+ * small fraction of the time of a full world and still makes a 128 by 128 thumbnail of a 512 world.
+ *
+ * It is APPROXIMATE and never the same as the generated world:
+ * water: assumed everywhere below the sea level, while the game only floods the oceans
+ * connected to the edges of the world and a number of random lakes
+ * caves: left out, though they can break through the surface, as are ore, lava and trees
+ * gravel: not shown, underwater columns are all Water
+ * stages: the classic Raising and Eroding run with the generator's settings, stages added
+ * or replaced with ClassicGenerator::stages are left out
+ * columns: with a step above 1, every column between two samples is taken to be the same
+ * as the sample before it
+ *
+ * Use it to pick seeds worth generating, not to answer questions about a world.
+ *
+ * heights: the y of the top of the ground in every sampled column, -1 if there is no ground
+ * surfaces: what the top of every sampled column looks like
+ * Both are laid out x / step + z / step * preview_width, the sampled columns X fastest then Z
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ApproximatePreview {
+    width: i32,
+    height: i32,
+    depth: i32,
+    step: i32,
+    sea_level: i32,
+    pub heights: Vec<i32>,
+    pub surfaces: Vec<SurfaceKind>
+}
+
+impl ApproximatePreview {
+
+    /**
+     * Builds the preview from the columns of RandomLevel::preview_columns
+     */
+    pub(crate) fn from_columns (width: i32, height: i32, depth: i32, step: i32, sea_level: i32, columns: &[(f64, f64, bool)]) -> Self {
+        let mut heights: Vec<i32> = Vec::with_capacity(columns.len());
+        let mut surfaces: Vec<SurfaceKind> = Vec::with_capacity(columns.len());
+
+        for (dirt, rock, sand) in columns {
+            //Soiling fills every tile at or below the higher of the two, which Growing then covers
+            let top: i32 = dirt.max(*rock).floor().clamp(-1.0, (height - 1) as f64) as i32;
+
+            let surface: SurfaceKind = if top < sea_level - 1 {
+                SurfaceKind::Water
+            } else if top == sea_level - 1 && *sand {
+                SurfaceKind::Sand
+            } else {
+                SurfaceKind::Grass
+            };
+
+            heights.push(top);
+            surfaces.push(surface);
+        }
+
+        ApproximatePreview { width, height, depth, step: step.max(1), sea_level, heights, surfaces }
+    }
+
+    pub fn width (&self) -> i32 { self.width }
+    pub fn height (&self) -> i32 { self.height }
+    pub fn depth (&self) -> i32 { self.depth }
+
+    /**
+     * Distance between two sampled columns, 1 if every column was sampled
+     */
+    pub fn step (&self) -> i32 { self.step }
+
+    /**
+     * Number of sampled columns along X and Z, the size of the thumbnail
+     */
+    pub fn preview_width (&self) -> i32 { (self.width + self.step - 1) / self.step }
+    pub fn preview_depth (&self) -> i32 { (self.depth + self.step - 1) / self.step }
+
+    /**
+     * Height of the surface of the sea, the water is assumed to be at every y below it
+     */
+    pub fn sea_level (&self) -> i32 { self.sea_level }
+
+    /**
+     * Always true, a reminder that nothing in a preview is exact
+     */
+    pub fn is_approximate (&self) -> bool { true }
+
+    fn index (&self, x: i32, z: i32) -> Option<usize> {
+        if x < 0 || z < 0 || x >= self.width || z >= self.depth { return None; }
+        return Some((x / self.step + z / self.step * self.preview_width()) as usize);
+    }
+
+    /**
+     * Returns the approximate y of the top of the ground at x, z, from the
+     * sampled column at or before it
+     */
+    pub fn height_at (&self, x: i32, z: i32) -> Option<i32> {
+        return self.index(x, z).map(|i| self.heights[i]);
+    }
+
+    /**
+     * Returns what the top of the column at x, z approximately looks like
+     */
+    pub fn surface_at (&self, x: i32, z: i32) -> Option<SurfaceKind> {
+        return self.index(x, z).map(|i| self.surfaces[i]);
+    }
+
+    /**
+     * Returns the approximate depth of the water above the ground at x, z, 0 on land
+     */
+    pub fn water_depth (&self, x: i32, z: i32) -> i32 {
+        return self.height_at(x, z).map_or(0, |y| (self.sea_level - 1 - y).max(0));
+    }
+
+    /**
+     * Fraction of the columns that are under water, from 0 to 1
+     */
+    pub fn water_fraction (&self) -> f64 {
+        let water: usize = self.surfaces.iter().filter(|s| **s == SurfaceKind::Water).count();
+        return water as f64 / self.surfaces.len().max(1) as f64;
+    }
+
+    /**
+     * Returns a thumbnail of the preview, preview_width by preview_depth, 3 bytes of red,
+     * green and blue for every sampled column in the same order as heights. Land is shaded lighter the higher it is, and water
+     * darker the deeper it is
+     */
+    pub fn to_rgb (&self) -> Vec<u8> {
+        let mut rgb: Vec<u8> = Vec::with_capacity(self.surfaces.len() * 3);
+
+        for (surface, y) in self.surfaces.iter().zip(&self.heights) {
+            let shade: f64 = if *surface == SurfaceKind::Water {
+                1.0 - ((self.sea_level - 1 - y) as f64 / 32.0).min(0.6)
+            } else {
+                0.7 + ((y - self.sea_level) as f64 / 64.0).clamp(0.0, 0.3)
+            };
+
+            for channel in surface.colour() {
+                rgb.push((channel as f64 * shade).round() as u8);
+            }
+        }
+
+        return rgb;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::ClassicGenerator;
+
+    #[test]
+    fn step_below_one_samples_every_column () {
+        let generator: ClassicGenerator = ClassicGenerator::new(7).width(48).depth(32);
+        let full: ApproximatePreview = generator.preview(1).unwrap();
+
+        assert_eq!((full.step(), full.preview_width(), full.preview_depth()), (1, 48, 32));
+        assert_eq!(full.heights.len(), 48 * 32);
+        for step in [0, -1, i32::MIN] {
+            assert_eq!(generator.preview(step).unwrap(), full);
+        }
+    }
+
+    #[test]
+    fn step_above_the_size_samples_one_column () {
+        let generator: ClassicGenerator = ClassicGenerator::new(7).width(48).depth(32);
+        let full: ApproximatePreview = generator.preview(1).unwrap();
+
+        for step in [48, 49, 1000, i32::MAX] {
+            let preview: ApproximatePreview = generator.preview(step).unwrap();
+            assert_eq!((preview.step(), preview.preview_width(), preview.preview_depth()), (48, 1, 1));
+            assert_eq!(preview.heights, vec![full.heights[0]]);
+            assert_eq!(preview.height_at(47, 31), full.height_at(0, 0));
+            assert_eq!(preview.to_rgb().len(), 3);
+        }
+    }
+
+    #[test]
+    fn columns_between_samples_take_the_sample_before () {
+        let generator: ClassicGenerator = ClassicGenerator::new(7).width(48).depth(32);
+        let full: ApproximatePreview = generator.preview(1).unwrap();
+        let preview: ApproximatePreview = generator.preview(5).unwrap();
+
+        assert_eq!((preview.preview_width(), preview.preview_depth()), (10, 7));
+        for (x, z) in [(0, 0), (4, 4), (5, 0), (47, 31), (23, 17)] {
+            assert_eq!(preview.height_at(x, z), full.height_at(x / 5 * 5, z / 5 * 5));
+            assert_eq!(preview.surface_at(x, z), full.surface_at(x / 5 * 5, z / 5 * 5));
+        }
+        for (x, z) in [(-1, 0), (0, -1), (48, 0), (0, 32)] {
+            assert_eq!(preview.height_at(x, z), None);
+            assert_eq!(preview.water_depth(x, z), 0);
+        }
+        assert!(preview.is_approximate());
+    }
+}
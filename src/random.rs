@@ -1,3 +1,8 @@
+/**
+ * Random struct is the Park-Miller generator the js worker seeds from the world
+ * seed. Seeds are taken modulo 2147483647, and seeds at or below 0 after that
+ * have 2147483646 added, so several seeds give the same sequence
+ */
 pub struct Random {
     pub rand: i64
 }
@@ -11,9 +16,19 @@ impl Random {
         }
     }
 
+    /**
+     * Returns the seed Random::new reduces a seed to, from 0 to 2147483646.
+     * Seeds with the same effective seed give the same sequence and the same world,
+     * see SeedClass
+     */
+    pub fn effective_seed (seed: i64) -> i64 {
+        return Random::new(seed).rand;
+    }
+
     /**
     * Returns a pseudo-random value between 1 and 2^32 - 2.
     */
+    #[allow(clippy::should_implement_trait)] //Named after the js method, Random is not an iterator
     pub fn next (&mut self) -> i64 {
         self.rand = self.rand * 16807 % 2147483647;
         return self.rand;
@@ -1,4 +1,4 @@
-/**
+/*!
  * This is the ported world generation code from classic js
  * Credit to TheSunCat for the deobfuscated js code - see js source here: 
  * https://github.com/TheSunCat/Minecraft-Classic-Reversed/blob/master/assets/js/RandomLevelWorker.js
@@ -10,20 +10,33 @@
  * 
  * ***Known potential errors***
  * 
- * Parse int has been replaced by casting. However, there are edge cases
- * when a double is passed with a value below 0.000001. Rust treats this
- * correctly, parsing this as 0, however js parses this as 1. Why I have
- * no clue, and it is such an obscure edge case it has not been noticable
- * in testing
+ * Parse int used to be replaced by casting, which parses a double below
+ * 0.000001 as 0 where js parses it as 1, because js writes the number as
+ * "1e-7" before parsing it. Calls on doubles now go through jsmath::parse_int,
+ * which reads the number the same way js does. parseInt on values that are
+ * already integers is still a plain cast
  * 
- * sin() and cos() are slightly different in rust when compared to js. This
- * is a deep level, and there is no easy fix other than rewriting js's source
- * code and copying that sin and cos functionality. Again, this precision loss
- * is so minor it has not been noticeable in testing.
+ * sin() and cos() in rust come from the platform's libm, which can round the
+ * last bit differently to js. They now go through jsmath, a port of the fdlibm
+ * code the browsers use, so caves and ores match the browser bit for bit.
  */
 
+//Lints below are allowed so the port can be read side by side with the js source
+#![allow(clippy::approx_constant, clippy::assign_op_pattern, clippy::precedence, clippy::unnecessary_cast)]
+#![allow(clippy::identity_op, clippy::int_plus_one, clippy::needless_late_init, clippy::too_many_arguments)]
+
+use crate::block::Block;
+use crate::block_pos::BlockPos;
+use crate::generator::{CancelToken, Stage};
+use crate::jsmath::{self, parse_int_i32 as parse_int};
+use crate::pipeline::GenerationStage;
 use crate::random::Random;
-use std::collections::HashMap;
+use crate::settings::GenerationSettings;
+use crate::trace::{FloodFill, GenerationTrace, OreVein, Tree, VeinStep};
+
+use std::panic;
+use std::sync::Arc;
+use std::thread;
 
 //Creating the Distort struct
 struct Distort {
@@ -78,7 +91,7 @@ impl PerlinNoise {
         let mut pow: f64 = 1.0;
 
         for i in 0..self.levels {
-            value += self.noise_levels[i as usize].clone().get_value(x * pow, y * pow) / pow;
+            value += self.noise_levels[i as usize].get_value(x * pow, y * pow) / pow;
             pow /= 2.0;
         }
 
@@ -92,24 +105,20 @@ impl PerlinNoise {
 
 #[derive(Clone)]
 pub struct ImprovedNoise {
-    p: HashMap<usize, i32>
+    p: Vec<i32>
 }
 
 impl ImprovedNoise {
     pub fn new (random: f64) -> Self {
-        let mut p: HashMap<usize, i32> = HashMap::new();
-
-        for i in 0..256 {
-            p.insert(i, i as i32);
-        }
+        //The upper half is filled in below as a copy of the shuffled lower half
+        let mut p: Vec<i32> = (0..256).chain([0; 256]).collect();
 
         for i in 0..256 {
+            //random is below 1, so j is at most 256
             let j: i32 = (random * (256.0 - i as f64)).round() as i32 + i as i32;
-            let tmp: i32 = p.get(&i).copied().unwrap_or(0);
-            p.insert(i,p.get(&(j as usize)).copied().unwrap_or(0));
-            p.insert(j as usize,tmp);
+            p.swap(i, j as usize);
 
-            p.insert((i + 256) as usize, p.get(&i).copied().unwrap_or(0));
+            p[i + 256] = p[i];
 
         }
 
@@ -149,51 +158,161 @@ impl ImprovedNoise {
         let d5: f64 = self.fade_curve(d4);
         let d6: f64 = self.fade_curve(d3);
         let d7: f64 = self.fade_curve(d2);
-        let mut l: i32 = self.p.get(&(i as usize)).copied().unwrap_or(0) + j;
-        let i1: i32 = self.p.get(&(l as usize)).copied().unwrap_or(0) + k;
+        let mut l: i32 = self.p.get(i as usize).copied().unwrap_or(0) + j;
+        let i1: i32 = self.p.get(l as usize).copied().unwrap_or(0) + k;
+
+        l = self.p.get((l + 1) as usize).copied().unwrap_or(0) + k;
+        i = self.p.get((i + 1) as usize).copied().unwrap_or(0) + j;
+        j = self.p.get(i as usize).copied().unwrap_or(0) + k;
+        i = self.p.get((i + 1) as usize).copied().unwrap_or(0) + k;
+
+        return self.lerp(d7, self.lerp(d6, self.lerp(d5, self.grad(self.p.get(i1 as usize).copied().unwrap_or(0), d4, d3, d2), self.grad(self.p.get(j as usize).copied().unwrap_or(0), d4 - 1.0, d3, d2)), self.lerp(d5, self.grad(self.p.get(l as usize).copied().unwrap_or(0), d4, d3 - 1.0, d2), self.grad(self.p.get(i as usize).copied().unwrap_or(0), d4 - 1.0, d3 - 1.0, d2))), self.lerp(d6, self.lerp(d5, self.grad(self.p.get((i1 + 1) as usize).copied().unwrap_or(0), d4, d3, d2 - 1.0), self.grad(self.p.get((j + 1) as usize).copied().unwrap_or(0), d4 - 1.0, d3, d2 - 1.0)), self.lerp(d5, self.grad(self.p.get((l + 1) as usize).copied().unwrap_or(0), d4, d3 - 1.0, d2 - 1.0), self.grad(self.p.get((i + 1) as usize).copied().unwrap_or(0), d4 - 1.0, d3 - 1.0, d2 - 1.0)))); 
+    }
+}
+
+
+//Raising for one column, its height relative to the middle of the world
+fn raise_column (distort: &Distort, distort1: &Distort, perlinnoise: &PerlinNoise, f: f64, height_scale: f64, l: i32, i1: i32) -> f64 {
+    let d0: f64 = distort.get_value( l as f64 * f, i1 as f64 * f) / 8.0 - 8.0;
+    let mut d1: f64 = distort1.get_value( l as f64 * f, i1 as f64 * f) / 6.0 + 6.0;
+
+    if perlinnoise.get_value( l as f64, i1 as f64) / 8.0 > 0.0 {
+        d1 = d0;
+    }
+
+    let mut d2: f64;
+    d2 = f64::max(d0, d1) / 2.0; 
+
+    if d2 < 0.0 {
+        d2 *= 0.8;
+    }
+
+    return d2 * height_scale;
+}
+
+//Eroding for one column, i2 is its raised height
+fn erode_column (distort1: &Distort, distort2: &Distort, mut i2: f64, j1: i32, k1: i32) -> f64 {
+    let d3: f64 = distort1.get_value( (j1 << 1) as f64, (k1 << 1) as f64) / 8.0;
+
+    let l1: f64 = if distort2.get_value( (j1 << 1) as f64, (k1 << 1) as f64) > 0.0 {1.0} else {0.0};
+    if d3 > 2.0 {
+        i2 = ((((i2 - l1) / 2.0) as i32) << 1) as f64 + l1; //What on earth were you doing trying to bit shift a double???
+    }
+
+    return i2;
+}
+
+/**
+ * TerrainSampler struct evaluates Raising, Eroding and Soiling for single columns, without
+ * a level. Every noise of those stages is built from the noise seed alone, so a column
+ * gets the same heights as in a full world of that noise seed
+ */
+pub(crate) struct TerrainSampler {
+    distort: Distort,
+    distort1: Distort,
+    perlinnoise: PerlinNoise,
+    erode_distort1: Distort,
+    erode_distort2: Distort,
+    perlinnoise1: PerlinNoise,
+    f: f64,
+    height_scale: f64,
+    half_height: f64
+}
 
-        l = self.p.get(&((l + 1) as usize)).copied().unwrap_or(0) + k;
-        i = self.p.get(&((i + 1) as usize)).copied().unwrap_or(0) + j;
-        j = self.p.get(&(i as usize)).copied().unwrap_or(0) + k;
-        i = self.p.get(&((i + 1) as usize)).copied().unwrap_or(0) + k;
+impl TerrainSampler {
+
+    pub(crate) fn new (rand: f64, height: i32, settings: &GenerationSettings) -> Self {
+        TerrainSampler {
+            distort: Distort::new(PerlinNoise::new(rand, 8), PerlinNoise::new(rand, 8)),
+            distort1: Distort::new(PerlinNoise::new(rand, 8), PerlinNoise::new(rand, 8)),
+            perlinnoise: PerlinNoise::new(rand, 8),
+            erode_distort1: Distort::new(PerlinNoise::new(rand, 8), PerlinNoise::new(rand, 8)),
+            erode_distort2: Distort::new(PerlinNoise::new(rand, 8), PerlinNoise::new(rand, 8)),
+            perlinnoise1: PerlinNoise::new(rand, 8),
+            f: settings.distortion_frequency,
+            height_scale: settings.height_scale,
+            half_height: height as f64 / 2.0
+        }
+    }
 
-        return self.lerp(d7, self.lerp(d6, self.lerp(d5, self.grad(self.p.get(&(i1 as usize)).copied().unwrap_or(0), d4, d3, d2), self.grad(self.p.get(&(j as usize)).copied().unwrap_or(0), d4 - 1.0, d3, d2)), self.lerp(d5, self.grad(self.p.get(&(l as usize)).copied().unwrap_or(0), d4, d3 - 1.0, d2), self.grad(self.p.get(&(i as usize)).copied().unwrap_or(0), d4 - 1.0, d3 - 1.0, d2))), self.lerp(d6, self.lerp(d5, self.grad(self.p.get(&((i1 + 1) as usize)).copied().unwrap_or(0), d4, d3, d2 - 1.0), self.grad(self.p.get(&((j + 1) as usize)).copied().unwrap_or(0), d4 - 1.0, d3, d2 - 1.0)), self.lerp(d5, self.grad(self.p.get(&((l + 1) as usize)).copied().unwrap_or(0), d4, d3 - 1.0, d2 - 1.0), self.grad(self.p.get(&((i + 1) as usize)).copied().unwrap_or(0), d4 - 1.0, d3 - 1.0, d2 - 1.0)))); 
+    /**
+     * Returns the dirt height and the rock height Soiling fills the column at l, i1 up to
+     */
+    pub(crate) fn column (&self, l: i32, i1: i32) -> (f64, f64) {
+        let raised: f64 = raise_column(&self.distort, &self.distort1, &self.perlinnoise, self.f, self.height_scale, l, i1);
+        let i2: f64 = erode_column(&self.erode_distort1, &self.erode_distort2, raised, l, i1) + self.half_height;
+        let l2: f64 = i2 + (self.perlinnoise1.get_value( l as f64, i1 as f64) / 24.0) - 4.0;
+        return (i2, l2);
     }
 }
 
+/**
+ * Returns the permutation every ImprovedNoise of a noise seed shuffles into. All of the
+ * height map noise is built from it, so noise seeds with the same permutation give the
+ * same terrain
+ */
+pub(crate) fn noise_permutation (rand: f64) -> Vec<i32> {
+    let mut p: Vec<i32> = ImprovedNoise::new(rand).p;
+    p.truncate(256);
+    return p;
+}
 
-struct RandomLevel {
+/**
+ * RandomLevel struct is the world while it generates, what every GenerationStage works on.
+ * Custom stages read and change it through its pub methods: the tiles in the tile map
+ * layout, the height maps, the shared Random that caves, ores, water, lava and trees draw
+ * from, and the steps of the classic stages such as flood_fill and place_ore
+ */
+pub struct RandomLevel<'a> {
 
-    progress_string: String,
+    progress_stage: Stage,
     progress_percent: i32,
-    progress_tiles: HashMap<usize, u8>, 	
+    progress: &'a mut dyn FnMut(Stage, u8),
+    cancel: &'a CancelToken,
+    trace: Option<&'a mut GenerationTrace>,
+    threads: usize,
+    settings: GenerationSettings,
+    progress_tiles: Vec<u8>,
     x_size: i32,
     y_size: i32,
     z_size: i32,
     random: Random,
     rand: f64,
-    tiles: HashMap<usize, u8>,
-    fill_queue: HashMap<usize, i32>
+    tiles: Vec<u8>,
+    heights: Vec<f64>,
+    eroded_heights: Vec<f64>,
+    fill_queue: Vec<i32>,
+    classic: bool
 
 }
 
-impl RandomLevel {
+impl<'a> RandomLevel<'a> {
 
-    pub fn new (seed: i64, x_size: i32, z_size: i32, mut y_size: i32) -> RandomLevel {
-        let progress_string: String = String::from("");
+    pub(crate) fn new (seed: i64, x_size: i32, z_size: i32, y_size: i32, progress: &'a mut dyn FnMut(Stage, u8), cancel: &'a CancelToken, trace: Option<&'a mut GenerationTrace>, threads: usize, settings: &GenerationSettings) -> RandomLevel<'a> {
+        let progress_stage: Stage = Stage::Raising;
         let progress_percent: i32 = 0;
-        let progress_tiles: HashMap<usize, u8> = HashMap::new();
+        let progress_tiles: Vec<u8> = Vec::new();
 
-        y_size = 64;
+        //The flood fill hacks below are only needed to match js for the sizes the game offers,
+        //and are tuned for its sea level, so a custom one floods from the real positions instead
+        let classic: bool = x_size == z_size && y_size == 64 && matches!(x_size, 128 | 256 | 512) && settings.sea_level_for(y_size) == y_size / 2;
+        let settings: GenerationSettings = *settings;
 
         let mut random: Random = Random::new(seed);
         let rand: f64 = random.next_float();
-        let tiles: HashMap<usize, u8> = HashMap::new();
-        let fill_queue: HashMap<usize, i32> = HashMap::new();
+        let tiles: Vec<u8> = vec![0; x_size as usize * y_size as usize * z_size as usize];
+        let heights: Vec<f64> = Vec::new();
+        let eroded_heights: Vec<f64> = Vec::new();
+        let fill_queue: Vec<i32> = Vec::new();
 
         RandomLevel {
-            progress_string,
+            progress_stage,
             progress_percent,
+            progress,
+            cancel,
+            trace,
+            threads,
+            settings,
             progress_tiles,
             x_size,
             y_size,
@@ -201,49 +320,231 @@ impl RandomLevel {
             random,
             rand,
             tiles,
-            fill_queue
+            heights,
+            eroded_heights,
+            fill_queue,
+            classic
         }
 
     }
 
+    //Stands in for the messages the js worker posts back to the page
+    fn post_message (&mut self) {
+        (self.progress)(self.progress_stage, self.progress_percent.clamp(0, 100) as u8);
+    }
+
+    //progressRenderer.progressStage, a new stage starts at 0%
+    fn set_stage (&mut self, stage: Stage) {
+        self.progress_stage = stage;
+        self.progress_percent = 0;
+        self.post_message();
+    }
+
+    //Checked at the top of every long loop, a cancelled level stops wherever it is
+    fn cancelled (&self) -> bool {
+        return self.cancel.is_cancelled();
+    }
+
+    /**
+     * Evaluates f for every column and returns the results laid out like aint, x + z * x_size.
+     * Columns are visited x by x like the js loops, with progress posted for each one. With more
+     * than one thread, a batch of x values is split between threads at a time, which gives the
+     * same result as f never draws from random
+     */
+    fn map_columns<T: Copy + Default + Send, F: Fn(i32, i32) -> T + Sync> (&mut self, f: F) -> Vec<T> {
+        return self.map_grid(self.x_size, self.z_size, f);
+    }
+
+    //Same as map_columns over a grid of any size, x + z * x_size
+    fn map_grid<T: Copy + Default + Send, F: Fn(i32, i32) -> T + Sync> (&mut self, x_size: i32, z_size: i32, f: F) -> Vec<T> {
+        let threads: i32 = self.threads.clamp(1, x_size as usize) as i32;
+        let batch: i32 = if threads == 1 {1} else {threads * 4};
+
+        let mut values: Vec<T> = vec![T::default(); x_size as usize * z_size as usize];
+        let f: &F = &f;
+
+        let mut l: i32 = 0;
+        while l < x_size {
+            self.progress_percent = l * 100 / (x_size - 1).max(1);
+            self.post_message();
+            if self.cancelled() { return values; }
+
+            let end: i32 = (l + batch).min(x_size);
+
+            //Each x value gives a run of z_size results, in z order
+            let column = move |x: i32| (0..z_size).map(move |z| f(x, z));
+            let results: Vec<T> = if threads == 1 {
+                (l..end).flat_map(column).collect()
+            } else {
+                let per_thread: i32 = (end - l + threads - 1) / threads;
+                thread::scope(|scope| {
+                    let handles: Vec<_> = (l..end).step_by(per_thread as usize)
+                        .map(|start| scope.spawn(move || (start..(start + per_thread).min(end)).flat_map(column).collect::<Vec<T>>()))
+                        .collect();
+                    //A panic in f is passed on, as a missing batch would shift every column after it
+                    return handles.into_iter().flat_map(|handle| handle.join().unwrap_or_else(|e| panic::resume_unwind(e))).collect();
+                })
+            };
+
+            for (i, value) in results.into_iter().enumerate() {
+                let x: i32 = l + i as i32 / z_size;
+                let z: i32 = i as i32 % z_size;
+                values[(x + z * x_size) as usize] = value;
+            }
+
+            l = end;
+        }
+
+        return values;
+    }
+
+    pub fn width (&self) -> i32 {self.x_size}
+    pub fn height (&self) -> i32 {self.y_size}
+    pub fn depth (&self) -> i32 {self.z_size}
+
+    /**
+     * Returns every tile as a block id in the tile map layout, (y * depth + z) * width + x
+     */
+    pub fn tiles (&self) -> &[u8] {
+        return &self.tiles;
+    }
+
+    pub fn tiles_mut (&mut self) -> &mut [u8] {
+        return &mut self.tiles;
+    }
+
+    /**
+     * Returns the block at a position, or None if the position is
+     * outside of the world or the tile is not a known block id
+     */
+    pub fn get (&self, x: i32, y: i32, z: i32) -> Option<Block> {
+        if x < 0 || y < 0 || z < 0 || x >= self.x_size || y >= self.y_size || z >= self.z_size { return None; }
+        return Block::from_id(self.tiles[((y * self.z_size + z) * self.x_size + x) as usize]);
+    }
+
+    /**
+     * Sets the block at a position, returns false and leaves
+     * the world unchanged if the position is outside of it
+     */
+    pub fn set (&mut self, x: i32, y: i32, z: i32, block: Block) -> bool {
+        if x < 0 || y < 0 || z < 0 || x >= self.x_size || y >= self.y_size || z >= self.z_size { return false; }
+        self.tiles[((y * self.z_size + z) * self.x_size + x) as usize] = block.id();
+        return true;
+    }
+
+    /**
+     * Height of every column, laid out x + z * width. Raising fills it relative to the
+     * middle of the world, then Soiling replaces it with the height of the ground,
+     * which Growing and Planting build on
+     */
+    pub fn heights (&self) -> &[f64] {
+        return &self.heights;
+    }
+
+    pub fn heights_mut (&mut self) -> &mut Vec<f64> {
+        return &mut self.heights;
+    }
+
+    /**
+     * Height of every column after Eroding, which Soiling builds the ground from
+     */
+    pub fn eroded_heights (&self) -> &[f64] {
+        return &self.eroded_heights;
+    }
+
+    pub fn eroded_heights_mut (&mut self) -> &mut Vec<f64> {
+        return &mut self.eroded_heights;
+    }
+
+    /**
+     * The Random every stage after Soiling draws from in turn, seeded from the world
+     * seed. Drawing from it in a new stage changes everything placed after that stage
+     */
+    pub fn random (&mut self) -> &mut Random {
+        return &mut self.random;
+    }
+
+    /**
+     * The first float drawn from the seed, which seeds all of the noise
+     */
+    pub fn noise_seed (&self) -> f64 {
+        return self.rand;
+    }
+
+    pub fn settings (&self) -> &GenerationSettings {
+        return &self.settings;
+    }
+
+    /**
+     * The trace being recorded, if the world is generated with generate_traced,
+     * so custom stages can record what they place too
+     */
+    pub fn trace_mut (&mut self) -> Option<&mut GenerationTrace> {
+        return self.trace.as_deref_mut();
+    }
+
+    /**
+     * Reports how far the current stage is, from 0 to 100
+     */
+    pub fn set_progress (&mut self, percent: i32) {
+        self.progress_percent = percent;
+        self.post_message();
+    }
+
+    /**
+     * True once the world has been cancelled, long custom stages should
+     * check it now and then and return early
+     */
+    pub fn is_cancelled (&self) -> bool {
+        return self.cancelled();
+    }
+
+    //Writes outside of the world are dropped, reads outside of it fall back to the default of each call site
+    fn set_tile (&mut self, index: usize, tile: u8) {
+        if let Some(t) = self.tiles.get_mut(index) {
+            *t = tile;
+        }
+    }
+
     //grow
-    pub fn grow (&mut self, aint: HashMap<usize, f64>) {
+    pub fn grow (&mut self, aint: &[f64]) {
         let i: i32 = self.x_size;
         let j: i32 = self.z_size;
         let k: i32 = self.y_size;
         let perlin_noise: PerlinNoise = PerlinNoise::new(self.rand, 8);
         let perlin_noise1: PerlinNoise = PerlinNoise::new(self.rand, 8);
+        let sea_level: i32 = self.settings.sea_level_for(k);
+        let sand_threshold: f64 = self.settings.sand_threshold;
+        let gravel_threshold: f64 = self.settings.gravel_threshold;
 
-        for l in 0..i {
-            //this.progress(l * 100 / (this.xSize - 1));
-            self.progress_percent = l * 100 / (self.x_size - 1);
-            //self.postMessage(progress);
+        //Only the noise is spread over threads, the tiles are written below
+        let flags: Vec<(bool, bool)> = self.map_columns(|l, i1| (perlin_noise.get_value(l as f64, i1 as f64) > sand_threshold, perlin_noise1.get_value(l as f64, i1 as f64) > gravel_threshold));
+        if self.cancelled() { return; }
 
+        for l in 0..i {
             for i1 in 0..j {
-                let flag: bool = perlin_noise.get_value(l as f64, i1 as f64) > 8.0;
-                let flag1: bool = perlin_noise1.get_value(l as f64, i1 as f64) > 12.0;
+                let (flag, flag1): (bool, bool) = flags[(l + i1 * i) as usize];
                 let j1: i32;
                 //var k1 = parseInt( ((j1 = parseInt(aint[l + i1 * i],10)) * this.zSize + i1) * this.xSize + l, 10);
-                j1 = aint.get(&((l + i1 * i) as usize)).copied().unwrap_or(0.0) as i32;
+                j1 = parse_int(aint.get((l + i1 * i) as usize).copied().unwrap_or(0.0));
                 let k1: i32 = ((j1 * self.z_size + i1) * self.x_size + l) as i32;//parseInt()
                 let l1: i32;
 
-                // 7 waterid
                 //if (((l1 = parseInt(this.tiles[((j1 + 1) * this.zSize + i1) * this.xSize + l],10) & 255) == 7) && j1 <= k / 2 - 1 && flag1) {
-                l1 = (self.tiles.get(&((((j1 + 1) * self.z_size + i1) * self.x_size + l) as usize)).copied().unwrap_or(0)) as i32 & 255;//parseInt()
-                if (l1 == 7) && j1 <= k / 2 - 1 && flag1 {
+                l1 = (self.tiles.get((((j1 + 1) * self.z_size + i1) * self.x_size + l) as usize).copied().unwrap_or(0)) as i32 & 255;//parseInt()
+                if (l1 == Block::Water.id() as i32) && j1 <= sea_level - 1 && flag1 {
 
-                    self.tiles.insert(k1 as usize, 12);//(byte) Tile.gravel.id;
+                    self.set_tile(k1 as usize, Block::Gravel.id());
                 }
 
-                if l1 == 0 {
-                    let mut i2: i32 = 1;//Tile.grass.id;
+                if l1 == Block::Air.id() as i32 {
+                    let mut i2: Block = Block::Grass;
 
-                    if j1 <= k / 2 - 1 && flag {
-                        i2 = 11;//Tile.sand.id;
+                    if j1 <= sea_level - 1 && flag {
+                        i2 = Block::Sand;
                     }
 
-                    self.tiles.insert(k1 as usize, i2 as u8);
+                    self.set_tile(k1 as usize, i2.id());
                 }
             }
         }
@@ -252,23 +553,24 @@ impl RandomLevel {
     //melt
     pub fn melt (&mut self) {
         let mut _i: i32 = 0;
-        let j: f64 = self.x_size as f64 * self.z_size as f64 * self.y_size as f64 / 10000.0;
+        let j: f64 = self.x_size as f64 * self.z_size as f64 * self.y_size as f64 / 10000.0 * self.settings.lava_density;
 
         let mut k = 0.0;
         while k < j {
+            if self.cancelled() { return; }
             if k % 100.0 == 0.0 {
                 self.progress_percent = (k * 100.0 / (j - 1.0)) as i32;
-                //self.postMessage(progress);
+                self.post_message();
             }
 
-            let extray: i32 = 16;
+            let extray: i32 = if self.classic {16} else {0};
             let l: i32 = self.random.next_int(self.x_size);
             let i1: i32 = self.random.next_int(self.y_size / 2 - 4) + extray;
             let j1: i32 = self.random.next_int(self.z_size);
 
-            if self.tiles.get(&(((i1 * self.z_size + j1) * self.x_size + l) as usize)).copied().unwrap_or(0) == 0 {
+            if self.tiles.get(((i1 * self.z_size + j1) * self.x_size + l) as usize).copied().unwrap_or(0) == Block::Air.id() {
                 _i += 1;
-                self.flood_fill(l, i1, j1, 0, 17);
+                self.flood_fill(l, i1, j1, 0, Block::Lava);
 
             }
             k += 1.0;
@@ -276,14 +578,15 @@ impl RandomLevel {
     }
 
     //plant
-    pub fn plant (&mut self, aint: HashMap<usize, f64>) {
+    pub fn plant (&mut self, aint: &[f64]) {
         let i: i32 = self.x_size;
-        let j: f64 = self.x_size as f64 * self.z_size as f64 / 4000.0;
+        let j: f64 = self.x_size as f64 * self.z_size as f64 / 4000.0 * self.settings.tree_density;
 
         let mut k: f64=0.0;
         while k < j {
+            if self.cancelled() { return; }
             self.progress_percent = (k * 100.0 / (j - 1.0)) as i32;
-            //self.postMessage(progress);
+            self.post_message();
 
             let l: i32 = self.random.next_int(self.x_size);
             let i1: i32 = self.random.next_int(self.z_size);
@@ -297,7 +600,7 @@ impl RandomLevel {
                     l1 += self.random.next_int(6) - self.random.next_int(6);
 
                     if k1 >= 0 && l1 >= 0 && k1 < self.x_size && l1 < self.z_size {
-                        let j2: f64 = aint.get(&((k1 + l1 * i) as usize)).copied().unwrap_or(0.0) + 1.0;//No idea if this is supposed to be a float or an int...
+                        let j2: f64 = aint.get((k1 + l1 * i) as usize).copied().unwrap_or(0.0) + 1.0;//No idea if this is supposed to be a float or an int...
                         let k2: i32 = self.random.next_int(3) + 4;
                         let mut flag: bool = true;
 
@@ -318,7 +621,7 @@ impl RandomLevel {
                                 while j3 <= (l1 + b0) as f64 && flag {
                                     if i3 >= 0.0 && l2 >= 0.0 && j3 >= 0.0 && i3 < self.x_size as f64 && l2 < self.y_size as f64 && j3 < self.z_size as f64 {
                                         
-                                        if (self.tiles.get(&(((l2 * self.z_size as f64 + j3) * self.x_size as f64 + i3) as usize)).copied().unwrap_or(0) & 255) != 0 && (l2 * self.z_size as f64 + j3) * self.x_size as f64 + i3 == ((l2 * self.z_size as f64 + j3) * self.x_size as f64 + i3).floor() {
+                                        if (self.tiles.get(((l2 * self.z_size as f64 + j3) * self.x_size as f64 + i3) as usize).copied().unwrap_or(0) & 255) != 0 && (l2 * self.z_size as f64 + j3) * self.x_size as f64 + i3 == ((l2 * self.z_size as f64 + j3) * self.x_size as f64 + i3).floor() {
                                             flag = false;
                                         }
                                     } else {
@@ -334,14 +637,14 @@ impl RandomLevel {
 
                         if flag {
                             l2 = (j2 * self.z_size as f64 + l1 as f64) * self.x_size as f64 + k1 as f64;
-                            if (self.tiles.get(&((((j2 - 1.0) * self.z_size as f64 + l1 as f64) * self.x_size as f64 + k1 as f64) as usize)).copied().unwrap_or(0) & 255) == 1 && j2 < self.y_size as f64 - k2 as f64 - 1.0 && (((j2 - 1.0) * self.z_size as f64 + l1 as f64) * self.x_size as f64 + k1 as f64) == (((j2 - 1.0) * self.z_size as f64 + l1 as f64) * self.x_size as f64 + k1 as f64).floor() {
-                                self.tiles.insert((l2 - 1.0 * self.x_size as f64 * self.z_size as f64) as usize, 3);//(byte) Tile.dirt.id;
+                            if (self.tiles.get((((j2 - 1.0) * self.z_size as f64 + l1 as f64) * self.x_size as f64 + k1 as f64) as usize).copied().unwrap_or(0) & 255) == Block::Grass.id() && j2 < self.y_size as f64 - k2 as f64 - 1.0 && (((j2 - 1.0) * self.z_size as f64 + l1 as f64) * self.x_size as f64 + k1 as f64) == (((j2 - 1.0) * self.z_size as f64 + l1 as f64) * self.x_size as f64 + k1 as f64).floor() {
+                                self.set_tile((l2 - 1.0 * self.x_size as f64 * self.z_size as f64) as usize, Block::Dirt.id());
 
                                 i3 = j2 - 3.0 + k2 as f64;
                                 while i3 <= j2 + k2 as f64 {
 
                                     j3 = i3 - (j2 + k2 as f64);
-                                    let k3: i32 = (1.0 - j3 / 2.0) as i32;//parseInt()
+                                    let k3: i32 = parse_int(1.0 - j3 / 2.0);
                                     let mut l3: i32 = k1 - k3;
 
                                     while l3 <= k1 + k3 {
@@ -354,7 +657,7 @@ impl RandomLevel {
                                             let k4: i32 = (j4 - l1) as i32;//parseInt()
 
                                             if i32::abs(i4) != k3 || i32::abs(k4) != k3 || self.random.next_int(2) != 0 && j3 != 0.0 {
-                                                self.tiles.insert(((i3 * self.z_size as f64 + j4 as f64) * self.x_size as f64 + l3 as f64) as usize, 14);//(byte) Tile.leaves.id;
+                                                self.set_tile(((i3 * self.z_size as f64 + j4 as f64) * self.x_size as f64 + l3 as f64) as usize, Block::Leaves.id());
                                             }
                                             j4 += 1;
                                         }
@@ -363,9 +666,15 @@ impl RandomLevel {
                                     i3 += 1.0;
                                 }
 
+                                //The trunk is found from its tile index, like set_tile, as j2 is not always whole
+                                if let Some(trace) = self.trace.as_deref_mut() {
+                                    let trunk: i32 = l2 as i32;
+                                    trace.trees.push(Tree { pos: BlockPos::new(trunk % self.x_size, trunk / (self.x_size * self.z_size), trunk / self.x_size % self.z_size), height: k2 });
+                                }
+
                                 i3 = 0.0;
                                 while i3 < k2 as f64 {
-                                    self.tiles.insert((l2 + i3 * self.x_size as f64 * self.z_size as f64) as usize, 13);//(byte) Tile.treeTrunk.id;
+                                    self.set_tile((l2 + i3 * self.x_size as f64 * self.z_size as f64) as usize, Block::Log.id());
                                     i3 += 1.0;
                                 }
                             }
@@ -377,37 +686,44 @@ impl RandomLevel {
         }
     }
 
-    pub fn place_ore (&mut self, tile: u8, j: f64, k: f64, mut l: f64) {
-        l = self.x_size as f64;
+    pub fn place_ore (&mut self, tile: Block, j: f64, k: f64, _l: f64) {
+        let l: f64 = self.x_size as f64;
         let i1: f64 = self.z_size as f64;
         let j1: f64 = self.y_size as f64;
         let k1: f64 = l * i1 * j1 / 256.0 / 64.0 * j / 100.0;
 
         let mut l1 = 0.0;
         while l1 < k1 {
+            if self.cancelled() { return; }
             self.progress_percent = (l1 * 100.0 / (k1 - 1.0) / 4.0 + k * 100.0 / 4.0) as i32;
-            //self.postMessage(progress);
+            self.post_message();
 
             let mut f: f64 = self.random.next_float() * l;
             let mut f1: f64 = self.random.next_float() * j1;
             let mut f2: f64 = self.random.next_float() * i1;
-            let i2: i32 = ((self.random.next_float() + self.random.next_float()) * 75.0 * j / 100.0) as i32; //parseInt()
+            let i2: i32 = parse_int((self.random.next_float() + self.random.next_float()) * 75.0 * j / 100.0);
             let mut f3: f64 = self.random.next_float() * 3.141592653589793 * 2.0;
             let mut f4: f64 = 0.0;
             let mut f5: f64 = self.random.next_float() * 3.141592653589793 * 2.0;
             let mut f6: f64 = 0.0;
 
+            //Only built when tracing, the path of every vein is kept
+            let mut vein: Option<OreVein> = self.trace.is_some().then(|| OreVein { block: tile, path: Vec::new(), tiles: 0 });
+
             for j2 in 0..i2 {
-                f =  f + f64::sin(f3) * f64::cos(f5);
-                f2 = f2 + f64::cos(f3) * f64::cos(f5);
-                f1 = f1 + f64::sin(f5);
+                f =  f + jsmath::sin(f3) * jsmath::cos(f5);
+                f2 = f2 + jsmath::cos(f3) * jsmath::cos(f5);
+                f1 = f1 + jsmath::sin(f5);
                 f3 += f4 * 0.2;
                 f4 *= 0.9;
                 f4 = f4 + (self.random.next_float() - self.random.next_float());
                 f5 = (f5 + f6 * 0.5) * 0.5;
                 f6 *= 0.9;
                 f6 = f6 + (self.random.next_float() - self.random.next_float());
-                let f7: f64 = f64::sin(j2 as f64 * 3.141592653589793 / i2 as f64) * j / 100.0 + 1.0;
+                let f7: f64 = jsmath::sin(j2 as f64 * 3.141592653589793 / i2 as f64) * j / 100.0 + 1.0;
+                if let Some(vein) = vein.as_mut() {
+                    vein.path.push(VeinStep { x: f, y: f1, z: f2, radius: f7 });
+                }
 
                 let mut k2:f64  = (f - f7).round();
 
@@ -425,11 +741,14 @@ impl RandomLevel {
                             let f10: f64 = i3 - f2;
 
                             if f8 * f8 + f9 * f9 * 2.0 + f10 * f10 < f7 * f7 && k2 >= 1.0 && l2 >= 1.0 && i3 >= 1.0 && k2 < self.x_size as f64 - 1.0 && l2 < self.y_size as f64 - 1.0 && i3 < self.z_size as f64 - 1.0 {
-                                let j3: i32 = ((l2 * self.z_size as f64 + i3) * self.x_size as f64 + k2) as i32; //parseInt()
+                                let j3: i32 = parse_int((l2 * self.z_size as f64 + i3) * self.x_size as f64 + k2);
 
                                 //if (this.tiles[j3] == Tile.rock.id) {
-                                if self.tiles.get(&(j3 as usize)).copied().unwrap_or(0) == 2 {
-                                    self.tiles.insert(j3 as usize, tile);
+                                if self.tiles.get(j3 as usize).copied().unwrap_or(0) == Block::Stone.id() {
+                                    self.set_tile(j3 as usize, tile.id());
+                                    if let Some(vein) = vein.as_mut() {
+                                        vein.tiles += 1;
+                                    }
                                 }
                             }
                             i3 += 1.0;
@@ -439,60 +758,65 @@ impl RandomLevel {
                     k2 += 1.0;
                 }
             }
+
+            if let (Some(trace), Some(vein)) = (self.trace.as_deref_mut(), vein) {
+                trace.ore_veins.push(vein);
+            }
             l1 += 1.0;
         }
     }
 
-    pub fn flood_fill (&mut self, xc: i32, yc: i32, zc: i32, _unused: u8, tile: u8) -> i32 {
+    pub fn flood_fill (&mut self, xc: i32, yc: i32, zc: i32, _unused: u8, tile: Block) -> i32 {
 
-        let mut w_bits: i32 = 1;
-        let mut h_bits: i32 = 1;
+        self.fill_queue.clear();
 
-        while (1 << w_bits) < self.x_size { w_bits += 1; }
-        while (1 << h_bits) < self.y_size { h_bits += 1; }
+        let start: i32;
+        if self.classic {
+            //js packs the start with the bits of the height in place of the depth, the extray
+            //offsets passed in by the callers are tuned to land on the right tiles because of it
+            let mut h_bits: i32 = 1;
+            while (1 << h_bits) < self.y_size { h_bits += 1; }
 
-        let z_mask: i32 = self.z_size - 1;
-        let x_mask: i32 = self.x_size - 1;
-        let mut count: i32 = 1;
+            start = ((yc << h_bits) + zc) * self.x_size + xc;
+        } else {
+            if xc < 0 || yc < 0 || zc < 0 || xc >= self.x_size || yc >= self.y_size || zc >= self.z_size {
+                return 0;
+            }
 
-        self.fill_queue.insert(0, ((yc << h_bits) + zc << w_bits) + xc);
+            start = (yc * self.z_size + zc) * self.x_size + xc;
+        }
+        self.fill_queue.push(start);
 
         let mut k2: i32 = 0;
 
         let offset: i32 = self.x_size * self.z_size;
 
-        while count > 0 {
-
-            count -= 1;
-
-            let mut val: i32 = self.fill_queue.get(&(count as usize)).copied().unwrap_or(0);
+        while let Some(mut val) = self.fill_queue.pop() {
+            if self.cancelled() { return k2; }
 
-            let z: i32 = val >> w_bits & z_mask;
-            let l2: i32 = val >> w_bits + h_bits;
+            let z: i32 = val / self.x_size % self.z_size;
+            let l2: i32 = val / offset;
 
             let mut i3: i32;
             let mut j3: i32;
 
-            i3 = val & x_mask;
+            i3 = val % self.x_size;
             j3 = i3;
 
-            while i3 > 0 && self.tiles.get(&((val - 1) as usize)).copied().unwrap_or(255) == 0 {
+            while i3 > 0 && self.tiles.get((val - 1) as usize).copied().unwrap_or(255) == Block::Air.id() {
                 i3 -= 1;
                 val -= 1; 
             }
 
-            while j3 < self.x_size && self.tiles.get(&((val + j3 - i3) as usize)).copied().unwrap_or(255) == 0 {
+            while j3 < self.x_size && self.tiles.get((val + j3 - i3) as usize).copied().unwrap_or(255) == Block::Air.id() {
                 j3 += 1;
             }
 
-            let k3: i32 = val >> w_bits & z_mask;
-            let l3: i32 = val >> w_bits + h_bits;
+            let k3: i32 = val / self.x_size % self.z_size;
+            let l3: i32 = val / offset;
 
-            if k3 != z || l3 != l2 {
-                //System.out.println("hoooly fuck");
-                //console.log("hoooly fuck")
-                println!("hoooly fuck");
-            }
+            //The js logs a message here if the span left its row, which the scan above never lets happen
+            debug_assert!(k3 == z && l3 == l2, "flood fill span left its row");
 
             let mut flag: bool = false;
             let mut flag1: bool = false;
@@ -500,21 +824,18 @@ impl RandomLevel {
 
             k2 += j3 - i3;
 
-            i3 = i3;
-
             while i3 < j3 {
 
-                self.tiles.insert(val as usize, tile);
+                self.set_tile(val as usize, tile.id());
                 let mut flag3: bool;
 
                 if z > 0 {
 
-                    flag3 = self.tiles.get(&((val - self.x_size) as usize)).copied().unwrap_or(255) == 0;
+                    flag3 = self.tiles.get((val - self.x_size) as usize).copied().unwrap_or(255) == Block::Air.id();
 
                     if flag3 && !flag {
 
-                        self.fill_queue.insert(count as usize, val - self.x_size);
-                        count += 1; 
+                        self.fill_queue.push(val - self.x_size);
 
                     }
 
@@ -524,12 +845,11 @@ impl RandomLevel {
 
                 if z < self.z_size - 1 {
 
-                    flag3 = self.tiles.get(&((val + self.x_size) as usize)).copied().unwrap_or(255) == 0;
+                    flag3 = self.tiles.get((val + self.x_size) as usize).copied().unwrap_or(255) == Block::Air.id();
 
                     if flag3 && !flag1 {
 
-                        self.fill_queue.insert(count as usize, val + self.x_size);
-                        count += 1;
+                        self.fill_queue.push(val + self.x_size);
 
                     }
 
@@ -538,18 +858,17 @@ impl RandomLevel {
                 }
 
                 if l2 > 0 {
-                    let b2: u8 = self.tiles.get(&((val - offset) as usize)).copied().unwrap_or(255);
+                    let b2: u8 = self.tiles.get((val - offset) as usize).copied().unwrap_or(255);
 
                     //if (( tile == Tile.lava.id || tile == Tile.calmLava.id) && (b2 == Tile.water.id || b2 == Tile.calmWater.id)) {
-                    if ( tile == 17) && (b2 == 7) {
-                        self.tiles.insert((val - offset) as usize, 2);//Tile.rock.id;
+                    if (tile == Block::Lava) && (b2 == Block::Water.id()) {
+                        self.set_tile((val - offset) as usize, Block::Stone.id());
                     }
 
-                    flag3 = b2 == 0;
+                    flag3 = b2 == Block::Air.id();
                     if flag3 && !flag2 {
 
-                        self.fill_queue.insert(count as usize, val - offset);
-                        count += 1; 
+                        self.fill_queue.push(val - offset);
 
                     }
 
@@ -560,124 +879,106 @@ impl RandomLevel {
             }
         }
 
+        if k2 > 0 {
+            if let Some(trace) = self.trace.as_deref_mut() {
+                let fill: FloodFill = FloodFill { start: BlockPos::new(start % self.x_size, start / offset, start / self.x_size % self.z_size), tiles: k2 as usize };
+                if tile == Block::Lava { trace.lava_pockets.push(fill); } else { trace.water_bodies.push(fill); }
+            }
+        }
+
         return k2;
     }
 
-    pub fn create_level (&mut self) {
-        
-        self.progress_string = String::from("Raising..");
+    //Raising, the height of every column relative to the middle of the world
+    pub fn raise (&mut self) {
 
         let distort: Distort = Distort::new(PerlinNoise::new(self.rand, 8), PerlinNoise::new(self.rand, 8));
-        let mut distort1: Distort = Distort::new(PerlinNoise::new(self.rand, 8), PerlinNoise::new(self.rand, 8));
+        let distort1: Distort = Distort::new(PerlinNoise::new(self.rand, 8), PerlinNoise::new(self.rand, 8));
         let perlinnoise: PerlinNoise = PerlinNoise::new(self.rand, 8);
 
         // 256x256 array of world noise
-        let mut aint: HashMap<usize, f64> = HashMap::new();
+        let f: f64 = self.settings.distortion_frequency;
+        let height_scale: f64 = self.settings.height_scale;
 
-        let f: f64 = 1.3;
+        let aint: Vec<f64> = self.map_columns(|l, i1| raise_column(&distort, &distort1, &perlinnoise, f, height_scale, l, i1));
+        if self.cancelled() { return; }
 
-        let mut l: i32 = 0;
-        let mut i1: i32;
-
-        while l < self.x_size {
-            self.progress_percent = l * 100 / (self.x_size - 1);
-            //self.postMessage(progress);
-
-            i1 = 0;
-
-            while i1 < self.z_size {
-                let d0: f64 = distort.get_value( l as f64 * f, i1 as f64 * f) / 8.0 - 8.0;
-                let mut d1: f64 = distort1.get_value( l as f64 * f, i1 as f64 * f) / 6.0 + 6.0;
-
-                if perlinnoise.get_value( l as f64, i1 as f64) / 8.0 > 0.0 {
-                    d1 = d0;
-                }
-
-                let mut d2: f64;
-                d2 = f64::max(d0, d1) / 2.0; 
-
-                if d2 < 0.0 {
-                    d2 *= 0.8;
-                }
+        if let Some(trace) = self.trace.as_deref_mut() {
+            trace.raw_heights = aint.clone();
+        }
 
-                aint.insert((l + i1 * self.x_size) as usize, d2);
-                i1 += 1;
-            }
-            l += 1;
-        } 
+        self.heights = aint;
+    }
 
-        self.progress_string = String::from("Eroding..");
-        let mut aint1: HashMap<usize, f64> = aint.clone();
+    //Eroding, parts of the height map are flattened into terraces
+    pub fn erode (&mut self) {
 
-        distort1 = Distort::new(PerlinNoise::new(self.rand, 8), PerlinNoise::new(self.rand, 8));
+        let distort1: Distort = Distort::new(PerlinNoise::new(self.rand, 8), PerlinNoise::new(self.rand, 8));
         let distort2: Distort = Distort::new(PerlinNoise::new(self.rand, 8), PerlinNoise::new(self.rand, 8));
-        
-        let mut j1: i32;
-        let mut k1: i32;
-        let mut l1: f64;
-        let mut i2: f64;
 
-        j1 = 0;
-        while j1 < self.x_size {
-            
-            self.progress_percent = j1 * 100 / (self.x_size - 1);
-            //self.postMessage(progress);
+        let x_size: i32 = self.x_size;
+        let aint: Vec<f64> = std::mem::take(&mut self.heights);
 
-            k1 = 0;
-            while k1 < self.z_size {
-                let d3: f64 = distort1.get_value( (j1 << 1) as f64, (k1 << 1) as f64) / 8.0;
+        //Every column only reads and writes its own height, so the columns can be eroded in any order
+        let aint1: Vec<f64> = self.map_columns(|j1, k1| erode_column(&distort1, &distort2, aint.get((j1 + k1 * x_size) as usize).copied().unwrap_or(0.0), j1, k1));
+        self.heights = aint;
+        if self.cancelled() { return; }
 
-                l1 = if distort2.get_value( (j1 << 1) as f64, (k1 << 1) as f64) > 0.0 {1.0} else {0.0};
-                if d3 > 2.0 {
-                    i2 = (((((aint1.get(&((j1 + k1 * self.x_size) as usize)).copied().unwrap_or(0.0)) - l1) / 2.0) as i32) << 1) as f64 + l1; //What on earth were you doing trying to bit shift a double???
+        if let Some(trace) = self.trace.as_deref_mut() {
+            trace.eroded_heights = aint1.clone();
+        }
 
-                    aint1.insert((j1 + k1 * self.x_size) as usize, i2);
-                }
-                k1 += 1;
-            }
-            j1 += 1;
-        } 
+        self.eroded_heights = aint1;
+    }
 
-        self.progress_string = String::from("Soiling..");
+    //Soiling, every column is filled with stone and dirt up to its height
+    pub fn soil (&mut self) {
         //this.progressRenderer.progressStage("Soiling..");
 
         let j2: i32 = self.x_size;
-        let mut k2: i32 = self.z_size;
-
-        j1 = self.y_size;
+        let k2: i32 = self.z_size;
+        let j1: i32 = self.y_size;
         let perlinnoise1: PerlinNoise = PerlinNoise::new(self.rand, 8);
 
+        //Only the noise is spread over threads, the tiles are written below
+        let soil: Vec<f64> = self.map_columns(|l, i1| (perlinnoise1.get_value( l as f64, i1 as f64) / 24.0) - 4.0);
+        if self.cancelled() { return; }
+
+        //Stages before this one may have been replaced, so the height maps are made to fit the world
+        let aint1: Vec<f64> = std::mem::take(&mut self.eroded_heights);
+        let mut aint: Vec<f64> = std::mem::take(&mut self.heights);
+        aint.resize(j2 as usize * k2 as usize, 0.0);
+
+        let mut l1: f64;
+        let mut i2: f64;
         let mut l2: f64;
         let mut i3: f64;
 
-        l = 0;
+        let mut l: i32 = 0;
+        let mut i1: i32;
         while l < j2 {
-            //progress(l * 100 / (xSize - 1));
-            self.progress_percent = l * 100 / (self.x_size - 1);
-            //self.postMessage(progress);
-
             i1 = 0;
             while i1 < k2 {
-                l1 = (perlinnoise1.get_value( l as f64, i1 as f64) / 24.0) - 4.0;
-                i2 = aint1.get(&((l + i1 * j2) as usize)).copied().unwrap_or(0.0) + j1 as f64 / 2.0;
+                l1 = soil[(l + i1 * j2) as usize];
+                i2 = aint1.get((l + i1 * j2) as usize).copied().unwrap_or(0.0) + j1 as f64 / 2.0;
                 l2 = i2 + l1;
                 
-                aint.insert((l + i1 * j2) as usize, f64::max(i2, l2));
+                aint[(l + i1 * j2) as usize] = f64::max(i2, l2);
 
                 i3 = 0.0;
                 while (i3 as i32) < j1 {
                     let j3: i32 = (i3 as i32 * self.z_size + i1) * self.x_size + l;
-                    let mut k3: u8 = 0;
+                    let mut k3: Block = Block::Air;
 
                     if i3 <= i2 {
-                        k3 = 3;//Tile.dirt.id;
+                        k3 = Block::Dirt;
                     }
 
                     if i3 <= l2 {
-                        k3 = 2;//Tile.rock.id;
+                        k3 = Block::Stone;
                     }
 
-                    self.tiles.insert(j3 as usize, k3);
+                    self.set_tile(j3 as usize, k3.id());
                     i3 += 1.0;
                 }
                 i1 += 1;
@@ -685,19 +986,33 @@ impl RandomLevel {
             l += 1;
         } 
 
-        self.progress_string = String::from("Carving..");
+        if let Some(trace) = self.trace.as_deref_mut() {
+            trace.dirt_heights = aint1.iter().map(|h| h + j1 as f64 / 2.0).collect();
+            trace.rock_heights = trace.dirt_heights.iter().zip(&soil).map(|(h, s)| h + s).collect();
+            trace.surface_heights = aint.clone();
+        }
+
+        self.heights = aint;
+        self.eroded_heights = aint1;
+    }
+
+    //Carving, caves are carved out of the stone and ores are placed
+    pub fn carve (&mut self) {
         //this.progressRenderer.progressStage("Carving..");
 
-        k2 = self.x_size;
-        j1 = self.z_size;
-        k1 = self.y_size;
-        l = k2 * j1 * k1 / 256 / 64;
+        let k2: i32 = self.x_size;
+        let j1: i32 = self.z_size;
+        let k1: i32 = self.y_size;
+        let l: i32 = ((k2 * j1 * k1 / 256 / 64) as f64 * self.settings.cave_density) as i32;
 
-        i1 = 0;
+        let mut i3: f64;
+
+        let mut i1: i32 = 0;
         while i1 < l {
+            if self.cancelled() { return; }
             //progress(i1 * 100 / (l - 1) / 4);
-            self.progress_percent = i1 * 100 / (l - 1) / 4;
-            //self.postMessage(progress);
+            self.progress_percent = i1 * 100 / (l - 1).max(1) / 4;
+            self.post_message();
 
             let mut f1: f64 = self.random.next_float() * k2 as f64;
             let mut f2: f64 = self.random.next_float() * k1 as f64;
@@ -711,9 +1026,9 @@ impl RandomLevel {
 
             let mut l3: f64 = 0.0;
             while l3 < i3 {
-                f1 = f1 + f64::sin(f4) * f64::cos(f6);
-                f3 = f3 + f64::cos(f4) * f64::cos(f6);
-                f2 = f2 + f64::sin(f6);
+                f1 = f1 + jsmath::sin(f4) * jsmath::cos(f6);
+                f3 = f3 + jsmath::cos(f4) * jsmath::cos(f6);
+                f2 = f2 + jsmath::sin(f6);
                 f4 += f5 * 0.2;
                 f5 *= 0.9;
                 f5 = f5 + (self.random.next_float() - self.random.next_float());
@@ -724,12 +1039,12 @@ impl RandomLevel {
                     let f8: f64 = f1 + self.random.next_float() * 4.0 - 2.0;
                     let f9: f64 = f2 + self.random.next_float() * 4.0 - 2.0;
                     let f10: f64 = f3 + self.random.next_float() * 4.0 - 2.0;
-                    let f11: f64 = f64::sin( l3 * 3.141592653589793 / i3) * 2.5 + 1.0;
+                    let f11: f64 = jsmath::sin( l3 * 3.141592653589793 / i3) * 2.5 + 1.0;
 
-                    let mut i4: i32 = (f8 - f11) as i32; //parseInt()
-                    while i4 <= (f8 + f11) as i32 { //parseInt()
-                        let mut j4: i32 = (f9 - f11) as i32; //parseInt()
-                        while j4 <= (f9 + f11) as i32 { //parseInt()
+                    let mut i4: i32 = parse_int(f8 - f11);
+                    while i4 <= parse_int(f8 + f11) {
+                        let mut j4: i32 = parse_int(f9 - f11);
+                        while j4 <= parse_int(f9 + f11) {
                             let mut k4: f64 = f10 - f11;
                             while k4 <= (f10 + f11) {
                                 let f12: f64 = i4 as f64 - f8;
@@ -737,11 +1052,14 @@ impl RandomLevel {
                                 let f14: f64 = k4 - f10;
 
                                 if f12 * f12 + f13 * f13 * 2.0 + f14 * f14 < f11 * f11 && i4 >= 1 && j4 >= 1 && k4 >= 1.0 && i4 < self.x_size - 1 && j4 < self.y_size - 1 && k4 < self.z_size as f64 - 1.0 {
-                                    let l4: i32 = (((j4 as f64 * self.z_size as f64 + k4) * self.x_size as f64) + i4 as f64) as i32;//parseInt()
+                                    let l4: i32 = parse_int(((j4 as f64 * self.z_size as f64 + k4) * self.x_size as f64) + i4 as f64);
 
                                     //if (tiles[l4] == Tile.rock.id) {
-                                    if self.tiles.get(&(l4 as usize)).copied().unwrap_or(0) == 2 {
-                                        self.tiles.insert(l4 as usize, 0);
+                                    if self.tiles.get(l4 as usize).copied().unwrap_or(0) == Block::Stone.id() {
+                                        self.set_tile(l4 as usize, Block::Air.id());
+                                        if let Some(trace) = self.trace.as_deref_mut() {
+                                            trace.carved[l4 as usize] = true;
+                                        }
                                     }
                                 }
                                 k4 += 1.0;
@@ -756,82 +1074,182 @@ impl RandomLevel {
             i1 += 1;
         }
         
-        self.place_ore(20, 90.0, 1.0, 4.0); // coal - Known Issue that Ore Populates Incorrectly
-        self.place_ore(19, 70.0, 2.0, 4.0); // iron - Known Issue that Ore Populates Incorrectly
-        self.place_ore(18, 50.0, 3.0, 4.0); // gold - Known Issue that Ore Populates Incorrectly
+        self.place_ore(Block::CoalOre, self.settings.coal_ore, 1.0, 4.0); // Known Issue that Ore Populates Incorrectly
+        self.place_ore(Block::IronOre, self.settings.iron_ore, 2.0, 4.0); // Known Issue that Ore Populates Incorrectly
+        self.place_ore(Block::GoldOre, self.settings.gold_ore, 3.0, 4.0); // Known Issue that Ore Populates Incorrectly
+    }
 
-        self.progress_string = String::from("Watering..");
+    //Watering, oceans are flooded from the edges of the world and lakes from random spots
+    pub fn water (&mut self) {
         //this.progressRenderer.progressStage("Watering..");
         let _i5: f64 = self.random.next_float();//Math.random();
         let mut j5: i32 = 0;
 
-        l = 7;//Tile.calmWater.id;
+        let water: Block = Block::Water;//Tile.calmWater.id;
+        let sea_level: i32 = self.settings.sea_level_for(self.y_size);
         //this.progress(0);
 
         // hack for floodfill to work...
-        let mut extray = 64-35;
-        if self.x_size >= 256 {extray = 128-36};
-        if self.x_size >= 512 {extray = 256-37};
+        let mut extray = 0;
+        if self.classic {
+            extray = 64-35;
+            if self.x_size >= 256 {extray = 128-36};
+            if self.x_size >= 512 {extray = 256-37};
+        }
 
-        i1 = 0;
+        let mut i1: i32 = 0;
         while i1 < self.x_size {
-            j5 = j5 + self.flood_fill(i1, self.y_size / 2 - 1 + extray, 0, 0, l as u8) as i32 + self.flood_fill(i1, self.y_size / 2 - 1, self.z_size - 1 + extray, 0, l as u8) as i32;
+            if self.cancelled() { return; }
+            j5 = j5 + self.flood_fill(i1, sea_level - 1 + extray, 0, 0, water) as i32 + self.flood_fill(i1, sea_level - 1, self.z_size - 1 + extray, 0, water) as i32;
             i1 += 1;
         }
 
         i1 = 0;
         while i1 < self.z_size {
-            j5 = j5 + self.flood_fill(0, self.y_size / 2 - 1 + extray, i1, 0, l as u8) as i32 + self.flood_fill(self.x_size - 1, self.y_size / 2 - 1 + extray, i1, 0, l as u8) as i32;
+            if self.cancelled() { return; }
+            j5 = j5 + self.flood_fill(0, sea_level - 1 + extray, i1, 0, water) as i32 + self.flood_fill(self.x_size - 1, sea_level - 1 + extray, i1, 0, water) as i32;
             i1 += 1;
         } 
 
 
         let f1: f64 = self.x_size as f64 * self.z_size as f64 / 200.0;
 
-        l1 = 0.0;
+        let mut l1: f64 = 0.0;
         while l1 < f1 {
+            if self.cancelled() { return; }
             if l1 % 100.0 == 0.0 {
             	self.progress_percent = (l1 * 100.0 / (f1 - 1.0)) as i32;
-                //self.postMessage(progress);
+                self.post_message();
             }
 
             let i4: i32 = self.random.next_int(self.x_size); //i2
-            let l4: i32 = self.y_size / 2 - 1 - self.random.next_int(3) + extray; //l2
+            let l4: i32 = sea_level - 1 - self.random.next_int(3) + extray; //l2
             let i6: i32 = self.random.next_int(self.z_size); //i3
-            if self.tiles.get(&(((l4 * self.z_size + i6) * self.x_size + i4) as usize)).copied().unwrap_or(255) == 0 {
-                j5 = j5 + self.flood_fill(i4, l4, i6, 0, l as u8) as i32;
+            if self.tiles.get(((l4 * self.z_size + i6) * self.x_size + i4) as usize).copied().unwrap_or(255) == Block::Air.id() {
+                j5 = j5 + self.flood_fill(i4, l4, i6, 0, water) as i32;
             }
             l1 += 1.0;
         }
             	
         self.progress_percent = 100;
-        //self.postMessage(progress);
-
-        self.progress_string = String::from("Melting..");
-        //this.progressRenderer.progressStage("Melting..");
-        self.melt();
-        self.progress_string = String::from("Growing..");
-        //this.progressRenderer.progressStage("Growing..");
-        self.grow(aint.clone());
-        self.progress_string = String::from("Planting..");
-        //this.progressRenderer.progressStage("Planting..");
-        self.plant(aint.clone());
-
-        self.progress_tiles = self.tiles.clone();
-        
-        self.progress_string = String::from("");
-        //self.postMessage(progress);
+        self.post_message();
+    }
 
+    /**
+     * Runs Raising and Eroding for every step-th column along X and Z, then evaluates the noise
+     * Soiling and Growing use there without writing any tiles. Returns the dirt height, the rock
+     * height and whether Growing would place sand for each of those columns, laid out
+     * x / step + z / step * columns across. Every column only depends on its own noise, so
+     * the sampled columns get the same heights as in a full world. See ClassicGenerator::preview
+     */
+    pub(crate) fn preview_columns (&mut self, step: i32) -> Vec<(f64, f64, bool)> {
+        let terrain: TerrainSampler = TerrainSampler::new(self.rand, self.y_size, &self.settings);
+        let perlin_noise: PerlinNoise = PerlinNoise::new(self.rand, 8);
+        let sand_threshold: f64 = self.settings.sand_threshold;
+
+        let step: i32 = step.max(1);
+        let across: i32 = (self.x_size + step - 1) / step;
+        let down: i32 = (self.z_size + step - 1) / step;
+
+        return self.map_grid(across, down, |sx, sz| {
+            let (l, i1): (i32, i32) = (sx * step, sz * step);
+            let (i2, l2): (f64, f64) = terrain.column(l, i1);
+            return (i2, l2, perlin_noise.get_value(l as f64, i1 as f64) > sand_threshold);
+        });
+    }
+
+    /**
+     * Replaces the tiles and height maps, to carry on from the state another level was
+     * left in after the same stages. Tiles of the wrong length are ignored
+     */
+    pub(crate) fn restore (&mut self, tiles: &[u8], heights: &[f64], eroded_heights: &[f64]) {
+        if tiles.len() == self.tiles.len() {
+            self.tiles.copy_from_slice(tiles);
+        }
+        self.heights = heights.to_vec();
+        self.eroded_heights = eroded_heights.to_vec();
     }
+
+    /**
+     * Runs every stage in order. Each one starts at 0% under the Stage it reports
+     * as, and generation stops after any stage that left the level cancelled
+     */
+    pub fn create_level (&mut self, stages: &[Arc<dyn GenerationStage>]) {
+
+        for stage in stages {
+            self.set_stage(stage.stage());
+            stage.run(self);
+            if self.cancelled() { return; }
+        }
+
+        self.progress_tiles = std::mem::take(&mut self.tiles);
+
+        self.progress_percent = 100;
+        self.post_message();
+
+    }
+}
+
+/**
+ * Runs Raising and Eroding for every step-th column only, then the noise of Soiling
+ * and Growing, see RandomLevel::preview_columns. Returns None if the level was cancelled
+ */
+pub fn start_preview (width: i32, depth: i32, height: i32, seed: i64, step: i32, progress: &mut dyn FnMut(Stage, u8), cancel: &CancelToken, threads: usize, settings: &GenerationSettings) -> Option<Vec<(f64, f64, bool)>> {
+
+    let mut level = RandomLevel::new(seed, width, depth, height, progress, cancel, None, threads, settings);
+
+    level.set_stage(Stage::Raising);
+    let columns: Vec<(f64, f64, bool)> = level.preview_columns(step);
+    if cancel.is_cancelled() {
+        return None;
+    }
+
+    return Some(columns);
 }
 
-pub fn start_generation (world_size: i32, seed: i64) -> HashMap<usize, u8> { //{worldSize: worldSize, seed: props.seed, seedrandom: seedrandom}
-    
-    let width: i32 = world_size;
-    let depth: i32 = world_size;
-    let height: i32 = 64;
+pub fn start_generation (width: i32, depth: i32, height: i32, seed: i64, progress: &mut dyn FnMut(Stage, u8), cancel: &CancelToken, trace: Option<&mut GenerationTrace>, threads: usize, settings: &GenerationSettings, stages: &[Arc<dyn GenerationStage>]) -> Option<Vec<u8>> { //{worldSize: worldSize, seed: props.seed, seedrandom: seedrandom}
 
-    let mut level = RandomLevel::new(seed, width, depth, height);
-    level.create_level();
-    return level.progress_tiles;
-}
\ No newline at end of file
+    let mut level = RandomLevel::new(seed, width, depth, height, progress, cancel, trace, threads, settings);
+    level.create_level(stages);
+
+    //The tiles of a cancelled level are only partly generated
+    if cancel.is_cancelled() {
+        return None;
+    }
+
+    return Some(level.progress_tiles);
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn map_grid_passes_on_a_panic_in_a_thread () {
+        let cancel: CancelToken = CancelToken::new();
+        let settings: GenerationSettings = GenerationSettings::default();
+        let mut progress = |_: Stage, _: u8| ();
+        let mut level: RandomLevel = RandomLevel::new(1, 16, 16, 16, &mut progress, &cancel, None, 4, &settings);
+
+        let result = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+            level.map_columns(|x, z| if x == 9 && z == 3 { panic!("column failed") } else { x + z })
+        }));
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn map_grid_is_the_same_for_any_thread_count () {
+        let cancel: CancelToken = CancelToken::new();
+        let settings: GenerationSettings = GenerationSettings::default();
+
+        let mut grids: Vec<Vec<i32>> = Vec::new();
+        for threads in [1, 2, 3, 8] {
+            let mut progress = |_: Stage, _: u8| ();
+            let mut level: RandomLevel = RandomLevel::new(1, 37, 11, 16, &mut progress, &cancel, None, threads, &settings);
+            grids.push(level.map_columns(|x, z| x * 1000 + z));
+        }
+
+        assert_eq!(grids[0][5 + 7 * 37], 5007);
+        assert!(grids.iter().all(|grid| *grid == grids[0]));
+    }
+}
@@ -0,0 +1,147 @@
+use crate::error::Result;
+use crate::JSLevel;
+
+use std::fmt;
+
+/**
+ * SchemaVersion enum stores which layout a savedGame uses, read from its version key
+ * V1: {"worldSeed","changedBlocks","worldSize","version"} where every changed block is {a, bt},
+ * this is what classic.minecraft.net and the omniarchive builds write
+ * Unknown: any other version, the save is read with the V1 layout and any keys
+ * that layout does not know about are kept in JSLevel::extra
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SchemaVersion {
+    V1,
+    Unknown(u8)
+}
+
+impl SchemaVersion {
+
+    //The version written into new saves
+    pub const LATEST: SchemaVersion = SchemaVersion::V1;
+
+    pub fn from_version (version: u8) -> Self {
+        match version {
+            1 => SchemaVersion::V1,
+            _ => SchemaVersion::Unknown(version)
+        }
+    }
+
+    /**
+     * Returns the value of the version key for this schema
+     */
+    pub fn version (self) -> u8 {
+        match self {
+            SchemaVersion::V1 => 1,
+            SchemaVersion::Unknown(version) => version
+        }
+    }
+
+    /**
+     * True if the library knows the layout of this schema
+     */
+    pub fn is_known (self) -> bool {
+        return !matches!(self, SchemaVersion::Unknown(_));
+    }
+}
+
+impl fmt::Display for SchemaVersion {
+    fn fmt (&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchemaVersion::V1 => write!(f, "v1"),
+            SchemaVersion::Unknown(version) => write!(f, "unknown (version {})", version)
+        }
+    }
+}
+
+/**
+ * SavedGameInfo struct stores what load_saved_game found out about a save
+ * schema: the schema the version key points to
+ * extra_keys: top level keys the schema does not define, kept in JSLevel::extra
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SavedGameInfo {
+    pub schema: SchemaVersion,
+    pub extra_keys: Vec<String>
+}
+
+impl JSLevel {
+
+    /**
+     * Returns the schema this level's version key points to
+     */
+    pub fn schema (&self) -> SchemaVersion {
+        return SchemaVersion::from_version(self.version);
+    }
+}
+
+/**
+ * Version aware version of deserialize_saved_game. Loads the save and reports which
+ * schema it uses, along with any top level keys from newer game builds or forks
+ * that were kept so they are written back unchanged
+ */
+pub fn load_saved_game (json_string: &str) -> Result<(JSLevel, SavedGameInfo)> {
+    let level: JSLevel = serde_json::from_str(json_string)?;
+
+    let info: SavedGameInfo = SavedGameInfo {
+        schema: level.schema(),
+        extra_keys: level.extra.keys().cloned().collect()
+    };
+
+    return Ok((level, info));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::ChangePolicy;
+    use crate::tile_map::TileMap;
+    use crate::world_generator::{FlatGenerator, WorldGenerator};
+    use crate::writer::write_saved_game_json_with;
+
+    #[test]
+    fn maps_version_numbers_both_ways () {
+        assert_eq!(SchemaVersion::from_version(1), SchemaVersion::V1);
+        assert_eq!(SchemaVersion::LATEST.version(), 1);
+        for version in [0, 2, 255] {
+            let schema: SchemaVersion = SchemaVersion::from_version(version);
+            assert_eq!(schema, SchemaVersion::Unknown(version));
+            assert_eq!(schema.version(), version);
+            assert!(!schema.is_known());
+        }
+        assert!(SchemaVersion::V1.is_known());
+        assert_eq!(SchemaVersion::Unknown(7).to_string(), "unknown (version 7)");
+    }
+
+    #[test]
+    fn loads_a_v1_save_without_extra_keys () {
+        let (level, info): (JSLevel, SavedGameInfo) = load_saved_game(r#"{"worldSeed":3,"changedBlocks":{"p1_2_3":{"a":1,"bt":9}},"worldSize":128,"version":1}"#).unwrap();
+
+        assert_eq!(info, SavedGameInfo { schema: SchemaVersion::V1, extra_keys: Vec::new() });
+        assert_eq!(level.schema(), SchemaVersion::V1);
+        assert_eq!(level.changedBlocks.len(), 1);
+    }
+
+    #[test]
+    fn keeps_unknown_versions_and_their_keys () {
+        let json: &str = r#"{"worldSeed":3,"changedBlocks":{},"worldSize":128,"version":9,"spawn":{"x":1,"y":2},"name":"fork"}"#;
+        let (level, info): (JSLevel, SavedGameInfo) = load_saved_game(json).unwrap();
+
+        assert_eq!(info.schema, SchemaVersion::Unknown(9));
+        assert_eq!(info.extra_keys, vec!["spawn".to_string(), "name".to_string()]);
+
+        //Written back unchanged, extra keys after version in the order they were read
+        let generator: FlatGenerator = FlatGenerator::default();
+        let tile_map: TileMap = generator.generate_for_size(128, 3).unwrap();
+        let mut output: Vec<u8> = Vec::new();
+        write_saved_game_json_with(&mut output, &level, &tile_map, ChangePolicy::Minimal, &generator).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), json);
+    }
+
+    #[test]
+    fn still_needs_the_v1_keys () {
+        assert!(load_saved_game(r#"{"worldSeed":3,"worldSize":128,"version":2}"#).is_err());
+        assert!(load_saved_game(r#"{"worldSeed":3,"changedBlocks":{},"worldSize":128,"version":300}"#).is_err());
+    }
+}
@@ -0,0 +1,660 @@
+/*!
+ * Recovers the seed of a classic world from part of its terrain.
+ *
+ * Random::new takes seeds modulo 2147483647, so there are only 2147483647 different
+ * worlds of each size, one for each effective seed, see SeedClass. Every noise of the
+ * height map stages is built from the first float the Random draws, and only through
+ * the permutation the ImprovedNoise shuffles from it, which rounds that float 256 times.
+ * So the whole seed space only has 26666 different terrains, and SeedCracker checks the
+ * observed heights against each of them once, with the cheap Raising, Eroding and
+ * Soiling noise of just the observed columns.
+ *
+ * The seeds that share a matching terrain, about 80000 each, differ in everything drawn
+ * from the Random afterwards. Each one runs Carving from the shared soiled tiles, which
+ * already rules out many of them through the observed stone, ore, air and liquid, then
+ * Watering and Melting, after which those blocks have to match exactly, and only the
+ * rest run Growing and Planting for the full comparison.
+ */
+
+use crate::block::Block;
+use crate::block_pos::BlockPos;
+use crate::error::{Error, Result};
+use crate::generator::{CancelToken, Stage};
+use crate::pipeline::{ClassicStage, GenerationStage};
+use crate::random::Random;
+use crate::random_level_worker::{noise_permutation, RandomLevel, TerrainSampler};
+use crate::settings::GenerationSettings;
+use crate::tile_map::TileMap;
+use crate::WORLD_HEIGHT;
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+//Modulus of the Park-Miller generator
+const MODULUS: i64 = 2147483647;
+
+//Inverse of the Park-Miller multiplier 16807 modulo MODULUS
+const MULTIPLIER_INVERSE: i64 = 1407677000;
+
+/**
+ * SeedClass struct is every i64 seed Random::new reduces to the same effective seed,
+ * which all give the same world. Positive seeds reduce to their remainder modulo
+ * 2147483647, and seeds at or below 0 to that remainder plus 2147483646, so each
+ * class holds seeds spaced 2147483647 apart in both directions
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SeedClass {
+    effective: i64
+}
+
+impl SeedClass {
+
+    /**
+     * Returns the class of a seed
+     */
+    pub fn of (seed: i64) -> Self {
+        SeedClass { effective: Random::effective_seed(seed) }
+    }
+
+    /**
+     * The seed Random::new reduces every seed of the class to, from 0 to 2147483646
+     */
+    pub fn effective (&self) -> i64 {
+        return self.effective;
+    }
+
+    /**
+     * A seed of the class to put in a savedGame. This is the effective seed itself, except
+     * for 0 which Random::new only reaches from negative seeds such as -2147483646
+     */
+    pub fn representative (&self) -> i64 {
+        if self.effective == 0 { return 1 - MODULUS; }
+        return self.effective;
+    }
+
+    /**
+     * True if Random::new reduces the seed to this class
+     */
+    pub fn contains (&self, seed: i64) -> bool {
+        return Random::effective_seed(seed) == self.effective;
+    }
+
+    /**
+     * Returns every seed of the class from start up to but not including end, in order
+     */
+    pub fn seeds_in (&self, start: i64, end: i64) -> Vec<i64> {
+        let mut seeds: Vec<i64> = Vec::new();
+        if start >= end { return seeds; }
+
+        //Members are congruent to the effective seed, one above it, or 0 modulo MODULUS
+        let mut residues: Vec<i64> = vec![self.effective % MODULUS, (self.effective + 1) % MODULUS, 0];
+        residues.sort_unstable();
+        residues.dedup();
+
+        for residue in residues {
+            let first: i64 = start + (residue - start).rem_euclid(MODULUS);
+            let mut seed: i64 = first;
+            while seed < end {
+                if self.contains(seed) { seeds.push(seed); }
+                match seed.checked_add(MODULUS) {
+                    Some(next) => seed = next,
+                    None => break
+                }
+            }
+        }
+
+        seeds.sort_unstable();
+        return seeds;
+    }
+}
+
+impl fmt::Display for SeedClass {
+    fn fmt (&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let near: Vec<String> = self.seeds_in(-2 * MODULUS, 2 * MODULUS).iter().map(|seed| seed.to_string()).collect();
+        write!(f, "{}, the same world as {} and every seed 2147483647 apart from those", self.effective, near.join(", "))
+    }
+}
+
+/**
+ * Observation struct is what is known of a world whose seed is wanted
+ * world_size: width and depth of the world, 128, 256 or 512
+ * heights: the ground height of some columns as x, z, y, see Observation::ground_height
+ * blocks: blocks known to be at some positions
+ */
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Observation {
+    pub world_size: i32,
+    pub heights: Vec<(i32, i32, i32)>,
+    pub blocks: Vec<(BlockPos, Block)>
+}
+
+impl Observation {
+
+    pub fn new (world_size: i32) -> Self {
+        Observation { world_size, heights: Vec::new(), blocks: Vec::new() }
+    }
+
+    /**
+     * Adds the ground height of the column at x, z
+     */
+    pub fn height (mut self, x: i32, z: i32, y: i32) -> Self {
+        self.heights.push((x, z, y));
+        return self;
+    }
+
+    /**
+     * Adds a block known to be at a position
+     */
+    pub fn block (mut self, pos: BlockPos, block: Block) -> Self {
+        self.blocks.push((pos, block));
+        return self;
+    }
+
+    /**
+     * Observes a region of a tile map with the same coordinates as the world, such as a
+     * dump, recording the ground height of every column and every tile in the region
+     */
+    pub fn from_region (tile_map: &TileMap, x: i32, z: i32, width: i32, depth: i32) -> Self {
+        let mut observation: Observation = Observation::new(tile_map.width());
+
+        for cz in z.max(0)..(z + depth).min(tile_map.depth()) {
+            for cx in x.max(0)..(x + width).min(tile_map.width()) {
+                observation.heights.push((cx, cz, Observation::ground_height(tile_map, cx, cz)));
+                for y in 0..tile_map.height() {
+                    if let Some(block) = tile_map.get(cx, y, cz) {
+                        observation.blocks.push((BlockPos::new(cx, y, cz), block));
+                    }
+                }
+            }
+        }
+
+        return observation;
+    }
+
+    /**
+     * Returns the y of the highest tile in a column that is ground, anything but air,
+     * liquids, trees and plants, -1 if there is none. This is the height Soiling fills
+     * the column up to, unless a cave broke through the top of it
+     */
+    pub fn ground_height (tile_map: &TileMap, x: i32, z: i32) -> i32 {
+        let mut y: i32 = tile_map.height() - 1;
+        while y >= 0 && !tile_map.get(x, y, z).is_some_and(is_ground) {
+            y -= 1;
+        }
+        return y;
+    }
+
+    fn check (&self) -> Result<()> {
+        if !matches!(self.world_size, 128 | 256 | 512) {
+            return Err(Error::InvalidWorldSize(self.world_size));
+        }
+        if self.heights.is_empty() && self.blocks.is_empty() {
+            return Err(Error::InvalidObservation("nothing is observed".to_string()));
+        }
+
+        let size: i32 = self.world_size;
+        for (x, z, y) in &self.heights {
+            if *x < 0 || *z < 0 || *x >= size || *z >= size || *y < -1 || *y >= WORLD_HEIGHT {
+                return Err(Error::InvalidObservation(format!("height {} of column {}, {} is outside of the world", y, x, z)));
+            }
+        }
+        for (pos, _) in &self.blocks {
+            if pos.x < 0 || pos.z < 0 || pos.y < 0 || pos.x >= size || pos.z >= size || pos.y >= WORLD_HEIGHT {
+                return Err(Error::InvalidObservation(format!("block at {} is outside of the world", pos)));
+            }
+        }
+        return Ok(());
+    }
+}
+
+//True for the blocks ground height counts
+fn is_ground (block: Block) -> bool {
+    return !matches!(block,
+        Block::Air | Block::Water | Block::Lava | Block::Log | Block::Leaves |
+        Block::Sapling | Block::Dandelion | Block::Rose | Block::BrownMushroom | Block::RedMushroom
+    );
+}
+
+/**
+ * CrackResult struct is what SeedCracker::run found
+ * terrains: how many of the 26666 terrains matched the observed heights
+ * candidates: how many seeds share those terrains
+ * seeds: every seed that matched, one per SeedClass, by effective seed
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CrackResult {
+    pub terrains: usize,
+    pub candidates: u64,
+    pub seeds: Vec<SeedClass>
+}
+
+/**
+ * SeedCracker is a builder that searches every seed for the worlds that match an
+ * Observation, for example:
+ * SeedCracker::new(Observation::from_region(&dump, 0, 0, 32, 32)).run()
+ *
+ * threads: threads the seeds are checked on, 0 uses every core, defaults to 0
+ * height_tolerance: fraction of the observed heights that may differ, for columns a
+ * cave broke through or a player changed, defaults to 0.05
+ * block_tolerance: fraction of the observed blocks that may differ, defaults to 0
+ * cancel: stops the search, which then returns Error::Cancelled
+ * only_seeds: checks just these seeds instead of every seed, such as the seeds a
+ * SeedSearch found, which takes as long as the list is
+ *
+ * Only worlds generated with the classic settings and stages can be found. Heights
+ * narrow the search down to a few terrains and take seconds, blocks pick the seeds
+ * out of those terrains, which takes minutes as it runs Carving for every one of them
+ * and the rest of the stages only for the ones that get past it
+ */
+#[derive(Clone, Debug)]
+pub struct SeedCracker {
+    observation: Observation,
+    threads: usize,
+    height_tolerance: f64,
+    block_tolerance: f64,
+    cancel: CancelToken,
+    only_seeds: Option<Vec<i64>>
+}
+
+//A terrain that matched, with its permutation and the Random states that shuffle into it
+struct Terrain {
+    permutation: Vec<i32>,
+    states: Vec<i64>
+}
+
+impl SeedCracker {
+
+    pub fn new (observation: Observation) -> Self {
+        SeedCracker { observation, threads: 0, height_tolerance: 0.05, block_tolerance: 0.0, cancel: CancelToken::new(), only_seeds: None }
+    }
+
+    pub fn threads (mut self, threads: usize) -> Self {
+        self.threads = threads;
+        return self;
+    }
+
+    pub fn height_tolerance (mut self, fraction: f64) -> Self {
+        self.height_tolerance = fraction;
+        return self;
+    }
+
+    pub fn block_tolerance (mut self, fraction: f64) -> Self {
+        self.block_tolerance = fraction;
+        return self;
+    }
+
+    pub fn cancel_token (mut self, cancel: CancelToken) -> Self {
+        self.cancel = cancel;
+        return self;
+    }
+
+    pub fn only_seeds (mut self, seeds: Vec<i64>) -> Self {
+        self.only_seeds = Some(seeds);
+        return self;
+    }
+
+    /**
+     * Checks every terrain against the observed heights, then every seed of the
+     * matching terrains against the observed blocks, and returns the seeds that match
+     */
+    pub fn run (&self) -> Result<CrackResult> {
+        self.observation.check()?;
+
+        let terrains: Vec<Terrain> = match &self.only_seeds {
+            Some(seeds) => self.matching_terrains_of(seeds)?,
+            None => self.matching_terrains()?
+        };
+        return self.crack_terrains(terrains);
+    }
+
+    //Picks the seeds out of the terrains that matched the observed heights
+    fn crack_terrains (&self, terrains: Vec<Terrain>) -> Result<CrackResult> {
+        let candidates: u64 = terrains.iter().map(|terrain| terrain.states.len() as u64).sum();
+
+        let mut seeds: Vec<SeedClass> = Vec::new();
+        for terrain in &terrains {
+            if self.observation.blocks.is_empty() {
+                seeds.extend(terrain.states.iter().map(|state| SeedClass { effective: effective_of_state(*state) }));
+            } else {
+                seeds.extend(self.matching_seeds(terrain)?);
+            }
+        }
+
+        seeds.sort_unstable();
+        seeds.dedup();
+        return Ok(CrackResult { terrains: terrains.len(), candidates, seeds });
+    }
+
+    fn thread_count (&self) -> usize {
+        if self.threads == 0 {
+            return thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+        }
+        return self.threads;
+    }
+
+    //Runs f for every index below count across the threads and returns the indices it accepted, in order
+    fn filter_parallel<F: Fn(usize) -> bool + Sync> (&self, count: usize, f: F) -> Result<Vec<usize>> {
+        let next: AtomicUsize = AtomicUsize::new(0);
+        let accepted: Mutex<Vec<usize>> = Mutex::new(Vec::new());
+
+        thread::scope(|scope| {
+            for _ in 0..self.thread_count().min(count.max(1)) {
+                scope.spawn(|| {
+                    loop {
+                        let index: usize = next.fetch_add(1, Ordering::Relaxed);
+                        if index >= count || self.cancel.is_cancelled() { return; }
+                        if f(index) {
+                            accepted.lock().unwrap_or_else(|e| e.into_inner()).push(index);
+                        }
+                    }
+                });
+            }
+        });
+        if self.cancel.is_cancelled() { return Err(Error::Cancelled); }
+
+        let mut accepted: Vec<usize> = accepted.into_inner().unwrap_or_else(|e| e.into_inner());
+        accepted.sort_unstable();
+        return Ok(accepted);
+    }
+
+    /**
+     * Returns the terrains whose ground matches the observed heights. The permutation only
+     * changes where the float times 256 - i crosses a half for some i, so the float is
+     * split at those points and one float from between each pair stands for the rest
+     */
+    fn matching_terrains (&self) -> Result<Vec<Terrain>> {
+        let mut bounds: Vec<f64> = vec![0.0, 1.0];
+        for k in 1..=256 {
+            for m in 0..k {
+                bounds.push((2 * m + 1) as f64 / (2 * k) as f64);
+            }
+        }
+        bounds.sort_by(f64::total_cmp);
+        bounds.dedup();
+
+        let settings: GenerationSettings = GenerationSettings::default();
+        let matched: Vec<usize> = self.filter_parallel(bounds.len() - 1, |i| self.matches_heights((bounds[i] + bounds[i + 1]) / 2.0, &settings))?;
+
+        let mut terrains: Vec<Terrain> = Vec::new();
+        for i in matched {
+            let permutation: Vec<i32> = noise_permutation((bounds[i] + bounds[i + 1]) / 2.0);
+            let states: Vec<i64> = states_between(bounds[i], bounds[i + 1]).filter(|state| noise_permutation(float_of_state(*state)) == permutation).collect();
+            if !states.is_empty() {
+                terrains.push(Terrain { permutation, states });
+            }
+        }
+
+        return Ok(terrains);
+    }
+
+    /**
+     * Returns the terrains of the given seeds whose ground matches the observed heights,
+     * with just the states of those seeds
+     */
+    fn matching_terrains_of (&self, seeds: &[i64]) -> Result<Vec<Terrain>> {
+        let mut states: Vec<i64> = seeds.iter().map(|seed| Random::new(*seed).next()).collect();
+        states.sort_unstable();
+        states.dedup();
+
+        let mut by_permutation: BTreeMap<Vec<i32>, Vec<i64>> = BTreeMap::new();
+        for state in states {
+            by_permutation.entry(noise_permutation(float_of_state(state))).or_default().push(state);
+        }
+        let mut terrains: Vec<Terrain> = by_permutation.into_iter().map(|(permutation, states)| Terrain { permutation, states }).collect();
+        terrains.sort_by_key(|terrain| terrain.states[0]);
+
+        let settings: GenerationSettings = GenerationSettings::default();
+        let matched: Vec<usize> = self.filter_parallel(terrains.len(), |i| self.matches_heights(float_of_state(terrains[i].states[0]), &settings))?;
+
+        return Ok(terrains.into_iter().enumerate().filter(|(i, _)| matched.binary_search(i).is_ok()).map(|(_, terrain)| terrain).collect());
+    }
+
+    //True if the ground of the terrain shuffled from the float matches the observed heights
+    fn matches_heights (&self, float: f64, settings: &GenerationSettings) -> bool {
+        let heights: &[(i32, i32, i32)] = &self.observation.heights;
+        let allowed: usize = (heights.len() as f64 * self.height_tolerance).floor() as usize;
+        let terrain: TerrainSampler = TerrainSampler::new(float, WORLD_HEIGHT, settings);
+
+        let mut wrong: usize = 0;
+        for (x, z, y) in heights {
+            let (dirt, rock): (f64, f64) = terrain.column(*x, *z);
+            if dirt.max(rock).floor().clamp(-1.0, (WORLD_HEIGHT - 1) as f64) as i32 != *y {
+                wrong += 1;
+                if wrong > allowed { return false; }
+            }
+        }
+        return true;
+    }
+
+    /**
+     * Returns the seeds of a terrain that match the observed blocks. The soiled tiles are
+     * the same for every seed of the terrain, so they are generated once and each seed
+     * carries on from them
+     */
+    fn matching_seeds (&self, terrain: &Terrain) -> Result<Vec<SeedClass>> {
+        let size: i32 = self.observation.world_size;
+        let settings: GenerationSettings = GenerationSettings::default();
+        let cancel: &CancelToken = &self.cancel;
+
+        let first: SeedClass = SeedClass { effective: effective_of_state(terrain.states[0]) };
+        let mut progress = |_: Stage, _: u8| ();
+        let mut level: RandomLevel = RandomLevel::new(first.representative(), size, size, WORLD_HEIGHT, &mut progress, cancel, None, 1, &settings);
+        for stage in [Stage::Raising, Stage::Eroding, Stage::Soiling] {
+            ClassicStage(stage).run(&mut level);
+        }
+        if noise_permutation(level.noise_seed()) != terrain.permutation { return Ok(Vec::new()); }
+        let (tiles, heights, eroded_heights): (Vec<u8>, Vec<f64>, Vec<f64>) = (level.tiles().to_vec(), level.heights().to_vec(), level.eroded_heights().to_vec());
+        drop(level);
+
+        let blocks: &[(BlockPos, Block)] = &self.observation.blocks;
+        let allowed: usize = (blocks.len() as f64 * self.block_tolerance).floor() as usize;
+
+        let matched: Vec<usize> = self.filter_parallel(terrain.states.len(), |i| {
+            let seed: SeedClass = SeedClass { effective: effective_of_state(terrain.states[i]) };
+            let mut progress = |_: Stage, _: u8| ();
+            let mut level: RandomLevel = RandomLevel::new(seed.representative(), size, size, WORLD_HEIGHT, &mut progress, cancel, None, 1, &settings);
+            level.restore(&tiles, &heights, &eroded_heights);
+
+            ClassicStage(Stage::Carving).run(&mut level);
+            if count_wrong(&level, blocks, possible_after_carving) > allowed { return false; }
+
+            ClassicStage(Stage::Watering).run(&mut level);
+            ClassicStage(Stage::Melting).run(&mut level);
+            if count_wrong(&level, blocks, possible_after_melting) > allowed { return false; }
+
+            ClassicStage(Stage::Growing).run(&mut level);
+            ClassicStage(Stage::Planting).run(&mut level);
+            return count_wrong(&level, blocks, |block, tile| tile == Some(block)) <= allowed;
+        })?;
+
+        return Ok(matched.into_iter().map(|i| SeedClass { effective: effective_of_state(terrain.states[i]) }).collect());
+    }
+}
+
+//True if an observed block can still end up where Carving left tile. Carving also places
+//the ore, and later stages only fill air, except that Melting turns water under lava into stone
+fn possible_after_carving (block: Block, tile: Option<Block>) -> bool {
+    return match block {
+        Block::CoalOre | Block::IronOre | Block::GoldOre => tile == Some(block),
+        Block::Stone => matches!(tile, Some(Block::Stone) | Some(Block::Air)),
+        Block::Air | Block::Water | Block::Lava => tile == Some(Block::Air),
+        _ => true
+    };
+}
+
+//True if an observed block can still end up where Melting left tile. Growing and Planting
+//never make ore, liquids, stone or air, so those have to be there already
+fn possible_after_melting (block: Block, tile: Option<Block>) -> bool {
+    return match block {
+        Block::Stone | Block::CoalOre | Block::IronOre | Block::GoldOre | Block::Air | Block::Water | Block::Lava => tile == Some(block),
+        _ => true
+    };
+}
+
+//Counts the observed blocks the tiles of a level rule out
+fn count_wrong<F: Fn(Block, Option<Block>) -> bool> (level: &RandomLevel<'_>, blocks: &[(BlockPos, Block)], possible: F) -> usize {
+    return blocks.iter().filter(|(pos, block)| !possible(*block, level.get(pos.x, pos.y, pos.z))).count();
+}
+
+//The first float a Random draws when its first state after seeding is state
+fn float_of_state (state: i64) -> f64 {
+    return (state - 1) as f64 / 2147483646.0;
+}
+
+//The states whose first float may lie from low to high. Floats are rounded, so this takes
+//a few states past each end, which still have to be checked against the permutation
+fn states_between (low: f64, high: f64) -> RangeInclusive<i64> {
+    let first: i64 = ((low * (MODULUS - 1) as f64).floor() as i64 + 1 - 2).max(0);
+    let last: i64 = ((high * (MODULUS - 1) as f64).ceil() as i64 + 1 + 2).min(MODULUS - 1);
+    return first..=last;
+}
+
+//The effective seed whose first state after seeding is state
+fn effective_of_state (state: i64) -> i64 {
+    return state * MULTIPLIER_INVERSE % MODULUS;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate_tile_map;
+
+    //Seeds 1 to 249 all draw a first float below 1 / 512, so they share one terrain
+    const SAME_TERRAIN: RangeInclusive<i64> = 1..=8;
+
+    #[test]
+    fn seed_class_reduces_like_random () {
+        assert_eq!(SeedClass::of(123456789).effective(), 123456789);
+        assert_eq!(SeedClass::of(0).effective(), 2147483646);
+        assert_eq!(SeedClass::of(MODULUS).effective(), 2147483646);
+        assert_eq!(SeedClass::of(-1).effective(), 2147483645);
+        assert_eq!(SeedClass::of(1 - MODULUS).effective(), 0);
+
+        assert_eq!(SeedClass::of(5).representative(), 5);
+        assert_eq!(SeedClass::of(1 - MODULUS).representative(), 1 - MODULUS);
+        for seed in [0, 1, 5, -1, 1 - MODULUS, MODULUS, i64::MAX, i64::MIN] {
+            let class: SeedClass = SeedClass::of(seed);
+            assert_eq!(SeedClass::of(class.representative()), class);
+            assert!(class.contains(seed));
+        }
+    }
+
+    #[test]
+    fn seed_class_lists_its_seeds () {
+        let class: SeedClass = SeedClass::of(123456789);
+        assert_eq!(class.seeds_in(-2 * MODULUS, 2 * MODULUS), vec![-4171510504, -2024026857, 123456789, 2270940436]);
+        assert!(class.contains(123456789 + MODULUS));
+        assert!(!class.contains(123456790));
+        assert!(class.seeds_in(5, 5).is_empty());
+        assert!(class.seeds_in(10, -10).is_empty());
+        assert_eq!(class.seeds_in(123456789, 123456790), vec![123456789]);
+        assert!(class.to_string().starts_with("123456789, the same world as -4171510504, -2024026857, 123456789, 2270940436"));
+
+        //0 and the seeds congruent to it all reduce to 2147483646
+        assert_eq!(SeedClass::of(0).seeds_in(-MODULUS, MODULUS + 1), vec![-MODULUS, 0, MODULUS - 1, MODULUS]);
+        assert_eq!(SeedClass::of(1 - MODULUS).seeds_in(-2 * MODULUS, 2 * MODULUS), vec![1 - 2 * MODULUS, 1 - MODULUS]);
+    }
+
+    #[test]
+    fn multiplier_inverse_undoes_the_first_step () {
+        assert_eq!(16807 * MULTIPLIER_INVERSE % MODULUS, 1);
+        for seed in [1, 2, 16807, 123456789, MODULUS - 1, 1 - MODULUS, -5] {
+            let state: i64 = Random::new(seed).next();
+            assert_eq!(effective_of_state(state), Random::effective_seed(seed));
+        }
+    }
+
+    #[test]
+    fn state_window_covers_each_bound () {
+        for k in 1..=256 {
+            for m in 0..k {
+                let bound: f64 = (2 * m + 1) as f64 / (2 * k) as f64;
+                let states: RangeInclusive<i64> = states_between(bound, bound);
+                assert!(float_of_state(*states.start()) < bound, "window starts above {}", bound);
+                assert!(float_of_state(*states.end()) > bound, "window ends below {}", bound);
+                assert!(states.end() - states.start() <= 6);
+            }
+        }
+        assert_eq!(states_between(0.0, 0.0), 0..=3);
+        assert_eq!(*states_between(1.0, 1.0).end(), MODULUS - 1);
+        assert_eq!(float_of_state(1), 0.0);
+        assert!(float_of_state(MODULUS - 1) < 1.0);
+    }
+
+    #[test]
+    fn observation_check_rejects_what_is_outside_the_world () {
+        let ok: Observation = Observation::new(128).height(0, 127, -1).block(BlockPos::new(127, WORLD_HEIGHT - 1, 0), Block::Stone);
+        assert!(ok.check().is_ok());
+
+        assert!(matches!(Observation::new(100).height(0, 0, 10).check(), Err(Error::InvalidWorldSize(100))));
+        assert!(matches!(Observation::new(128).check(), Err(Error::InvalidObservation(_))));
+        for bad in [Observation::new(128).height(128, 0, 10), Observation::new(128).height(0, -1, 10), Observation::new(128).height(0, 0, -2), Observation::new(128).height(0, 0, WORLD_HEIGHT)] {
+            assert!(matches!(bad.check(), Err(Error::InvalidObservation(_))), "{:?}", bad.heights);
+        }
+        for pos in [BlockPos::new(0, -1, 0), BlockPos::new(0, WORLD_HEIGHT, 0), BlockPos::new(0, 0, 128), BlockPos::new(-1, 0, 0)] {
+            assert!(matches!(Observation::new(128).block(pos, Block::Air).check(), Err(Error::InvalidObservation(_))));
+        }
+    }
+
+    #[test]
+    fn cracks_a_seed_from_heights_and_a_few_blocks () {
+        let world: TileMap = generate_tile_map(128, 3).unwrap();
+        let region: Observation = Observation::from_region(&world, 40, 40, 8, 8);
+        let mut observation: Observation = Observation::new(128);
+        observation.heights = region.heights.clone();
+        observation.blocks = region.blocks.iter().filter(|(pos, _)| pos.x == 40 && pos.z == 40 && pos.y < 32).cloned().collect();
+
+        //A seed with another terrain is ruled out by the heights, the blocks pick 3 out of the rest
+        let seeds: Vec<i64> = SAME_TERRAIN.chain([123456789]).collect();
+        let found: CrackResult = SeedCracker::new(observation.clone()).threads(2).only_seeds(seeds.clone()).run().unwrap();
+        assert_eq!(found, CrackResult { terrains: 1, candidates: 8, seeds: vec![SeedClass::of(3)] });
+
+        observation.blocks.clear();
+        let heights_only: CrackResult = SeedCracker::new(observation).threads(2).only_seeds(seeds).run().unwrap();
+        assert_eq!(heights_only.seeds, SAME_TERRAIN.map(SeedClass::of).collect::<Vec<SeedClass>>());
+    }
+
+    #[test]
+    fn full_search_keeps_the_seed_of_a_known_world () {
+        //Its first float is exactly 1 / 2, a bound where the permutation changes
+        let seed: i64 = effective_of_state(1073741824);
+        assert_eq!(float_of_state(Random::new(seed).next()), 0.5);
+
+        let world: TileMap = generate_tile_map(128, seed).unwrap();
+        let mut observation: Observation = Observation::new(128);
+        observation.heights = Observation::from_region(&world, 60, 60, 8, 8).heights;
+
+        let cracker: SeedCracker = SeedCracker::new(observation).threads(0);
+        let terrains: Vec<Terrain> = cracker.matching_terrains().unwrap();
+        assert!(terrains.iter().any(|terrain| terrain.states.contains(&Random::new(seed).next())));
+
+        let found: CrackResult = cracker.crack_terrains(terrains).unwrap();
+        assert!(found.seeds.contains(&SeedClass::of(seed)));
+    }
+
+    #[test]
+    fn cracks_a_seed_where_lava_met_water () {
+        let settings: GenerationSettings = GenerationSettings::default();
+        let cancel: CancelToken = CancelToken::new();
+        let mut progress = |_: Stage, _: u8| ();
+        let mut level: RandomLevel = RandomLevel::new(1, 128, 128, WORLD_HEIGHT, &mut progress, &cancel, None, 1, &settings);
+        for stage in [Stage::Raising, Stage::Eroding, Stage::Soiling, Stage::Carving] {
+            ClassicStage(stage).run(&mut level);
+        }
+        let carved: Option<Block> = level.get(47, 15, 94);
+        drop(level);
+
+        //Carving left air here, Watering flooded it, and Melting turned the water under the lava into stone
+        let world: TileMap = generate_tile_map(128, 1).unwrap();
+        assert_eq!((carved, world.get(47, 15, 94)), (Some(Block::Air), Some(Block::Stone)));
+        assert!(possible_after_carving(Block::Stone, carved));
+
+        let observation: Observation = Observation::from_region(&world, 44, 91, 8, 8);
+        let found: CrackResult = SeedCracker::new(observation).threads(2).only_seeds(SAME_TERRAIN.collect()).run().unwrap();
+        assert_eq!(found.seeds, vec![SeedClass::of(1)]);
+    }
+}
@@ -0,0 +1,613 @@
+/*!
+ * Searches a range of seeds for worlds that match a set of predicates.
+ *
+ * Every seed in the range is handed to the predicates as a SeedCandidate, which
+ * generates the world with ClassicGenerator or an ApproximatePreview of it only when a
+ * predicate first asks for one, so predicates that only look at the preview never
+ * pay for a full world. Seeds are split into chunks that worker threads take in
+ * order, and the search keeps track of the first seed that has not been scanned
+ * yet, so a SearchCheckpoint written part way through can be resumed later.
+ */
+
+use crate::block::Block;
+use crate::error::{Error, Result};
+use crate::generator::{CancelToken, ClassicGenerator};
+use crate::preview::{ApproximatePreview, SurfaceKind};
+use crate::random::Random;
+use crate::spawn::{find_spawn_with, highest_tile, NO_SPAWN_Y};
+use crate::tile_map::TileMap;
+
+use std::cell::OnceCell;
+use std::collections::{BTreeSet, VecDeque};
+use std::fmt;
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/**
+ * A predicate scores a candidate seed, None rejects it. A seed matches when every
+ * predicate of the search accepts it, and is ranked by the sum of their scores
+ */
+pub type SeedPredicate = Arc<dyn Fn(&SeedCandidate) -> Option<f64> + Send + Sync>;
+
+/**
+ * SeedCandidate struct is a seed being looked at by the predicates of a search.
+ * The world and the preview are generated the first time they are asked for
+ * and shared by every predicate after that
+ */
+pub struct SeedCandidate {
+    seed: i64,
+    world_size: i32,
+    preview_step: i32,
+    world: OnceCell<TileMap>,
+    preview: OnceCell<ApproximatePreview>
+}
+
+impl SeedCandidate {
+
+    pub fn new (seed: i64, world_size: i32, preview_step: i32) -> Self {
+        SeedCandidate { seed, world_size, preview_step, world: OnceCell::new(), preview: OnceCell::new() }
+    }
+
+    pub fn seed (&self) -> i64 { self.seed }
+    pub fn world_size (&self) -> i32 { self.world_size }
+
+    /**
+     * Returns the world of the seed, generated with ClassicGenerator
+     */
+    pub fn world (&self) -> Result<&TileMap> {
+        if let Some(world) = self.world.get() { return Ok(world); }
+
+        let world: TileMap = ClassicGenerator::new(self.seed).size(self.world_size).generate()?;
+        return Ok(self.world.get_or_init(|| world));
+    }
+
+    /**
+     * Returns the approximate preview of the seed, sampling every
+     * preview_step-th column, see ClassicGenerator::preview
+     */
+    pub fn preview (&self) -> Result<&ApproximatePreview> {
+        if let Some(preview) = self.preview.get() { return Ok(preview); }
+
+        let preview: ApproximatePreview = ClassicGenerator::new(self.seed).size(self.world_size).preview(self.preview_step)?;
+        return Ok(self.preview.get_or_init(|| preview));
+    }
+}
+
+/**
+ * SeedMatch struct is a seed that every predicate accepted
+ * seed: the seed
+ * score: the sum of the scores the predicates gave it, higher ranks first
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SeedMatch {
+    pub seed: i64,
+    pub score: f64
+}
+
+/**
+ * SearchCheckpoint struct is how far a search got, enough to resume it
+ * world_size: width and depth of the worlds searched
+ * start: the first seed of the search
+ * end: the seed the search stops before
+ * next_seed: every seed below this one has been scanned
+ * matches: the ranked matches found so far
+ *
+ * As text it is a few lines of "key value", with a "match seed score" line for
+ * every match, lines starting with # are comments. The predicates are not saved,
+ * so resume with the same ones the search started with
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchCheckpoint {
+    pub world_size: i32,
+    pub start: i64,
+    pub end: i64,
+    pub next_seed: i64,
+    pub matches: Vec<SeedMatch>
+}
+
+impl SearchCheckpoint {
+
+    /**
+     * True if every seed of the search has been scanned
+     */
+    pub fn is_finished (&self) -> bool {
+        return self.next_seed >= self.end;
+    }
+
+    pub fn load (path: &Path) -> Result<Self> {
+        return fs::read_to_string(path)?.parse();
+    }
+
+    /**
+     * Writes the checkpoint next to path first and then moves it over path,
+     * so a search stopped while writing leaves the last checkpoint intact
+     */
+    pub fn save (&self, path: &Path) -> Result<()> {
+        let temp: PathBuf = path.with_extension("tmp");
+        fs::write(&temp, self.to_string())?;
+        fs::rename(&temp, path)?;
+        return Ok(());
+    }
+}
+
+impl fmt::Display for SearchCheckpoint {
+    fn fmt (&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "# seed search checkpoint")?;
+        writeln!(f, "world_size {}", self.world_size)?;
+        writeln!(f, "start {}", self.start)?;
+        writeln!(f, "end {}", self.end)?;
+        writeln!(f, "next_seed {}", self.next_seed)?;
+        for m in &self.matches {
+            writeln!(f, "match {} {}", m.seed, m.score)?;
+        }
+        return Ok(());
+    }
+}
+
+impl FromStr for SearchCheckpoint {
+    type Err = Error;
+
+    fn from_str (text: &str) -> Result<SearchCheckpoint> {
+        let mut world_size: Option<i32> = None;
+        let mut start: Option<i64> = None;
+        let mut end: Option<i64> = None;
+        let mut next_seed: Option<i64> = None;
+        let mut matches: Vec<SeedMatch> = Vec::new();
+
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+            let invalid = || Error::InvalidCheckpoint(line.to_string());
+            let parts: Vec<&str> = line.split_whitespace().collect();
+
+            match parts.as_slice() {
+                ["world_size", size] => world_size = Some(size.parse().map_err(|_| invalid())?),
+                ["start", seed] => start = Some(seed.parse().map_err(|_| invalid())?),
+                ["end", seed] => end = Some(seed.parse().map_err(|_| invalid())?),
+                ["next_seed", seed] => next_seed = Some(seed.parse().map_err(|_| invalid())?),
+                ["match", seed, score] => matches.push(SeedMatch { seed: seed.parse().map_err(|_| invalid())?, score: score.parse().map_err(|_| invalid())? }),
+                _ => return Err(invalid())
+            }
+        }
+
+        let missing = |key: &str| Error::InvalidCheckpoint(format!("missing {}", key));
+        return Ok(SearchCheckpoint {
+            world_size: world_size.ok_or_else(|| missing("world_size"))?,
+            start: start.ok_or_else(|| missing("start"))?,
+            end: end.ok_or_else(|| missing("end"))?,
+            next_seed: next_seed.ok_or_else(|| missing("next_seed"))?,
+            matches
+        });
+    }
+}
+
+/**
+ * SearchOutcome struct is the result of SeedSearch::run
+ * matches: the best matches, highest score first, ties broken by the lower seed
+ * scanned: how many seeds the predicates were run on in this run
+ * checkpoint: where the search got to, resume from it with SeedSearch::resume_from
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchOutcome {
+    pub matches: Vec<SeedMatch>,
+    pub scanned: u64,
+    pub checkpoint: SearchCheckpoint
+}
+
+//What the workers share while a search runs
+struct SearchState {
+    next_seed: i64,
+    done: BTreeSet<i64>,
+    matches: Vec<SeedMatch>,
+    found: usize,
+    scanned: u64,
+    last_saved: Instant
+}
+
+/**
+ * SeedSearch is a builder for a search over the seeds from start up to but not
+ * including end, for example the first million seeds with a lake near the middle
+ * of a 256 world and at least 400 gold ore:
+ * SeedSearch::new(256, 0, 1000000).predicate(lake_near_centre(200)).predicate(block_count_at_least(Block::GoldOre, 400)).run()
+ *
+ * threads: threads that scan seeds, 0 uses every core, defaults to 0
+ * preview_step: the step of SeedCandidate::preview, defaults to 4
+ * limit: how many of the best matches are kept, defaults to 100
+ * stop_after: the search stops early once this many seeds have matched
+ * checkpoint: a file the progress is saved to every checkpoint_every, 10 seconds
+ * by default, and when the search stops
+ * cancel: stops the search, which still returns what it found
+ *
+ * Random::new takes seeds modulo 2147483647, so different seeds can give the same
+ * world. A seed is skipped when the seed Random reduces it to is also in the range,
+ * so every world in the range is only scanned once
+ */
+#[derive(Clone)]
+pub struct SeedSearch {
+    world_size: i32,
+    start: i64,
+    end: i64,
+    resume_at: i64,
+    threads: usize,
+    preview_step: i32,
+    limit: usize,
+    stop_after: Option<usize>,
+    checkpoint: Option<PathBuf>,
+    checkpoint_every: Duration,
+    cancel: CancelToken,
+    predicates: Vec<SeedPredicate>,
+    matches: Vec<SeedMatch>
+}
+
+impl SeedSearch {
+
+    //Seeds a worker takes at a time
+    const CHUNK: i64 = 16;
+
+    pub fn new (world_size: i32, start: i64, end: i64) -> Self {
+        SeedSearch {
+            world_size,
+            start,
+            end,
+            resume_at: start,
+            threads: 0,
+            preview_step: 4,
+            limit: 100,
+            stop_after: None,
+            checkpoint: None,
+            checkpoint_every: Duration::from_secs(10),
+            cancel: CancelToken::new(),
+            predicates: Vec::new(),
+            matches: Vec::new()
+        }
+    }
+
+    /**
+     * Continues a search from a checkpoint, with the matches it had already found.
+     * The world size and range are taken from the checkpoint
+     */
+    pub fn resume_from (mut self, checkpoint: &SearchCheckpoint) -> Self {
+        self.world_size = checkpoint.world_size;
+        self.start = checkpoint.start;
+        self.end = checkpoint.end;
+        self.resume_at = checkpoint.next_seed.max(checkpoint.start);
+        self.matches = checkpoint.matches.clone();
+        return self;
+    }
+
+    pub fn threads (mut self, threads: usize) -> Self {
+        self.threads = threads;
+        return self;
+    }
+
+    pub fn preview_step (mut self, step: i32) -> Self {
+        self.preview_step = step.max(1);
+        return self;
+    }
+
+    pub fn limit (mut self, limit: usize) -> Self {
+        self.limit = limit;
+        return self;
+    }
+
+    pub fn stop_after (mut self, matches: usize) -> Self {
+        self.stop_after = Some(matches);
+        return self;
+    }
+
+    pub fn checkpoint<P: AsRef<Path>> (mut self, path: P) -> Self {
+        self.checkpoint = Some(path.as_ref().to_path_buf());
+        return self;
+    }
+
+    pub fn checkpoint_every (mut self, interval: Duration) -> Self {
+        self.checkpoint_every = interval;
+        return self;
+    }
+
+    pub fn cancel_token (mut self, cancel: CancelToken) -> Self {
+        self.cancel = cancel;
+        return self;
+    }
+
+    /**
+     * Adds a predicate, see SeedPredicate
+     */
+    pub fn predicate<F: Fn(&SeedCandidate) -> Option<f64> + Send + Sync + 'static> (mut self, predicate: F) -> Self {
+        self.predicates.push(Arc::new(predicate));
+        return self;
+    }
+
+    /**
+     * Scores one seed, None if a predicate rejected it
+     */
+    pub fn evaluate (&self, seed: i64) -> Option<f64> {
+        let candidate: SeedCandidate = SeedCandidate::new(seed, self.world_size, self.preview_step);
+        let mut score: f64 = 0.0;
+        for predicate in &self.predicates {
+            score += predicate(&candidate)?;
+        }
+        return Some(score);
+    }
+
+    //True if the seed gives the same world as another seed in the range that is scanned instead
+    fn is_duplicate (&self, seed: i64) -> bool {
+        let effective: i64 = Random::effective_seed(seed);
+        return effective != seed && (self.start..self.end).contains(&effective);
+    }
+
+    /**
+     * Scans the range across the threads and returns the ranked matches. Stops early
+     * when stop_after is reached or the search is cancelled, the checkpoint of the
+     * outcome then tells where to carry on from. If a predicate panics, the search
+     * stops, saves its checkpoint from before the seeds it had not finished, and
+     * passes the panic on
+     */
+    pub fn run (&self) -> Result<SearchOutcome> {
+        if !matches!(self.world_size, 128 | 256 | 512) {
+            return Err(Error::InvalidWorldSize(self.world_size));
+        }
+
+        let threads: usize = if self.threads == 0 {
+            thread::available_parallelism().map(|n| n.get()).unwrap_or(1)
+        } else {
+            self.threads
+        };
+
+        let next_chunk: AtomicI64 = AtomicI64::new(self.resume_at);
+        let stop: AtomicBool = AtomicBool::new(false);
+        let state: Mutex<SearchState> = Mutex::new(SearchState {
+            next_seed: self.resume_at,
+            done: BTreeSet::new(),
+            matches: self.matches.clone(),
+            found: 0,
+            scanned: 0,
+            last_saved: Instant::now()
+        });
+
+        //A panicking predicate stops every worker, its chunk is never marked as done
+        let joined: Vec<thread::Result<Result<()>>> = thread::scope(|scope| {
+            let handles: Vec<_> = (0..threads).map(|_| scope.spawn(|| {
+                let worked: thread::Result<Result<()>> = panic::catch_unwind(AssertUnwindSafe(|| self.work(&next_chunk, &stop, &state)));
+                if !matches!(worked, Ok(Ok(()))) { stop.store(true, Ordering::Relaxed); }
+                return worked;
+            })).collect();
+            return handles.into_iter().map(|handle| handle.join().unwrap_or_else(Err)).collect();
+        });
+
+        let state: SearchState = state.into_inner().unwrap_or_else(|e| e.into_inner());
+        let checkpoint: SearchCheckpoint = self.checkpoint_of(&state);
+        if let Some(path) = &self.checkpoint {
+            checkpoint.save(path)?;
+        }
+
+        //The checkpoint is saved first, so a search that panicked resumes from before the chunk it was in
+        let mut result: Result<()> = Ok(());
+        for worked in joined {
+            match worked {
+                Ok(worked) => result = result.and(worked),
+                Err(payload) => panic::resume_unwind(payload)
+            }
+        }
+        result?;
+
+        return Ok(SearchOutcome { matches: checkpoint.matches.clone(), scanned: state.scanned, checkpoint });
+    }
+
+    //Takes chunks of seeds until the range runs out or the search is stopped
+    fn work (&self, next_chunk: &AtomicI64, stop: &AtomicBool, state: &Mutex<SearchState>) -> Result<()> {
+        loop {
+            let chunk: i64 = next_chunk.fetch_add(Self::CHUNK, Ordering::Relaxed);
+            if chunk >= self.end || stop.load(Ordering::Relaxed) { return Ok(()); }
+
+            let mut found: Vec<SeedMatch> = Vec::new();
+            let mut scanned: u64 = 0;
+            let mut finished: bool = true;
+            for seed in chunk..(chunk + Self::CHUNK).min(self.end) {
+                if stop.load(Ordering::Relaxed) || self.cancel.is_cancelled() {
+                    finished = false;
+                    break;
+                }
+                if self.is_duplicate(seed) { continue; }
+
+                scanned += 1;
+                if let Some(score) = self.evaluate(seed) {
+                    found.push(SeedMatch { seed, score });
+                }
+            }
+
+            let mut guard = state.lock().unwrap_or_else(|e| e.into_inner());
+            let state: &mut SearchState = &mut guard;
+            state.scanned += scanned;
+            state.found += found.len();
+            for m in found {
+                insert_ranked(&mut state.matches, m, self.limit);
+            }
+
+            //Only a chunk that was scanned to the end counts towards the checkpoint
+            if finished {
+                state.done.insert(chunk);
+                while state.done.remove(&state.next_seed) {
+                    state.next_seed = (state.next_seed + Self::CHUNK).min(self.end);
+                }
+            }
+
+            if self.cancel.is_cancelled() || self.stop_after.is_some_and(|n| state.found >= n) {
+                stop.store(true, Ordering::Relaxed);
+                return Ok(());
+            }
+
+            if let Some(path) = &self.checkpoint {
+                if state.last_saved.elapsed() >= self.checkpoint_every {
+                    state.last_saved = Instant::now();
+                    self.checkpoint_of(state).save(path)?;
+                }
+            }
+        }
+    }
+
+    fn checkpoint_of (&self, state: &SearchState) -> SearchCheckpoint {
+        SearchCheckpoint { world_size: self.world_size, start: self.start, end: self.end, next_seed: state.next_seed, matches: state.matches.clone() }
+    }
+}
+
+impl fmt::Debug for SeedSearch {
+    fn fmt (&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SeedSearch")
+            .field("world_size", &self.world_size)
+            .field("start", &self.start)
+            .field("end", &self.end)
+            .field("threads", &self.threads)
+            .field("predicates", &self.predicates.len())
+            .finish_non_exhaustive()
+    }
+}
+
+//Adds a match in ranked order, replacing an earlier match of the same seed, and keeps the best limit
+fn insert_ranked (matches: &mut Vec<SeedMatch>, m: SeedMatch, limit: usize) {
+    matches.retain(|other| other.seed != m.seed);
+    let index: usize = matches.partition_point(|other| other.score > m.score || other.score == m.score && other.seed < m.seed);
+    matches.insert(index, m);
+    matches.truncate(limit);
+}
+
+/**
+ * Accepts worlds with at least count tiles of a block, scored by how many there are.
+ * Generates the full world
+ */
+pub fn block_count_at_least (block: Block, count: usize) -> impl Fn(&SeedCandidate) -> Option<f64> + Send + Sync + 'static {
+    return move |candidate: &SeedCandidate| {
+        let found: usize = candidate.world().ok()?.blocks().iter().filter(|b| **b == block).count();
+        return (found >= count).then_some(found as f64);
+    };
+}
+
+/**
+ * Accepts worlds whose spawn is on an island, land that does not reach the edge of the
+ * world, of at most max_area columns. Smaller islands score higher, from 0 to 1.
+ * Generates the full world, land is every column find_spawn would accept as a spawn.
+ * The game's spawn is random, so the one checked comes from find_spawn_with a Random
+ * seeded with the candidate's seed, which keeps the score of a seed the same every run
+ */
+pub fn spawn_on_island (max_area: usize) -> impl Fn(&SeedCandidate) -> Option<f64> + Send + Sync + 'static {
+    return move |candidate: &SeedCandidate| {
+        let world: &TileMap = candidate.world().ok()?;
+        let spawn = find_spawn_with(world, &mut Random::new(candidate.seed()));
+        if spawn.y == NO_SPAWN_Y { return None; }
+
+        let water_level: i32 = world.height() / 2;
+        let land = |x: i32, z: i32| highest_tile(world, x, z) + 1 > water_level;
+        let mut seen: Vec<bool> = vec![false; (world.width() * world.depth()) as usize];
+        let (area, open) = flood_columns(world.width(), world.depth(), spawn.x, spawn.z, max_area, &mut seen, land);
+
+        if open || area == 0 || area > max_area { return None; }
+        return Some(1.0 - area as f64 / (world.width() * world.depth()) as f64);
+    };
+}
+
+/**
+ * Accepts worlds with a lake of at least min_area columns that reaches into the
+ * middle half of the world, water that does not reach the edge, scored by the area
+ * of the biggest one. Only looks at the preview, so the lakes are the approximate
+ * ones of ApproximatePreview and min_area is counted in world columns
+ */
+pub fn lake_near_centre (min_area: usize) -> impl Fn(&SeedCandidate) -> Option<f64> + Send + Sync + 'static {
+    return move |candidate: &SeedCandidate| {
+        let preview: &ApproximatePreview = candidate.preview().ok()?;
+        let (width, depth, step): (i32, i32, i32) = (preview.preview_width(), preview.preview_depth(), preview.step());
+        let water = |x: i32, z: i32| preview.surfaces[(x + z * width) as usize] == SurfaceKind::Water;
+
+        let mut seen: Vec<bool> = vec![false; (width * depth) as usize];
+        let mut best: usize = 0;
+        for z in depth / 4..depth * 3 / 4 {
+            for x in width / 4..width * 3 / 4 {
+                if seen[(x + z * width) as usize] || !water(x, z) { continue; }
+
+                let (area, open) = flood_columns(width, depth, x, z, usize::MAX, &mut seen, water);
+                let area: usize = area * (step * step) as usize;
+                if !open && area >= min_area { best = best.max(area); }
+            }
+        }
+
+        return (best > 0).then_some(best as f64);
+    };
+}
+
+/**
+ * Counts the columns connected to x, z that are inside and not seen yet, marking them as
+ * seen and stopping past limit, and returns whether any of them is on the edge of the grid
+ */
+fn flood_columns<F: Fn(i32, i32) -> bool> (width: i32, depth: i32, x: i32, z: i32, limit: usize, seen: &mut [bool], inside: F) -> (usize, bool) {
+    let mut queue: VecDeque<(i32, i32)> = VecDeque::from([(x, z)]);
+    let mut area: usize = 0;
+    let mut open: bool = false;
+
+    while let Some((x, z)) = queue.pop_front() {
+        if x < 0 || z < 0 || x >= width || z >= depth || seen[(x + z * width) as usize] || !inside(x, z) { continue; }
+        seen[(x + z * width) as usize] = true;
+
+        area += 1;
+        open |= x == 0 || z == 0 || x == width - 1 || z == depth - 1;
+        if area > limit { break; }
+
+        queue.extend([(x + 1, z), (x - 1, z), (x, z + 1), (x, z - 1)]);
+    }
+
+    return (area, open);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate_tile_map;
+
+    fn temp_checkpoint (name: &str) -> PathBuf {
+        return std::env::temp_dir().join(format!("mc-classic-js-{}-{}.txt", name, std::process::id()));
+    }
+
+    #[test]
+    fn candidate_world_is_the_generated_world () {
+        let candidate: SeedCandidate = SeedCandidate::new(11, 128, 4);
+        assert_eq!(candidate.world().unwrap(), &generate_tile_map(128, 11).unwrap());
+    }
+
+    #[test]
+    fn checkpoint_round_trips_through_text () {
+        let checkpoint: SearchCheckpoint = SearchCheckpoint { world_size: 256, start: -5, end: 100, next_seed: 48, matches: vec![SeedMatch { seed: 7, score: 2.5 }, SeedMatch { seed: -3, score: 1.0 }] };
+
+        assert_eq!(checkpoint.to_string().parse::<SearchCheckpoint>().unwrap(), checkpoint);
+        assert!(!checkpoint.is_finished());
+        assert!(matches!("world_size 256\nstart 0\nend 1".parse::<SearchCheckpoint>(), Err(Error::InvalidCheckpoint(_))));
+        assert!(matches!("world_size 256\nstart 0\nend 1\nnext_seed 0\nmatch 1".parse::<SearchCheckpoint>(), Err(Error::InvalidCheckpoint(_))));
+    }
+
+    #[test]
+    fn panicking_predicate_fails_the_search_and_keeps_its_seeds () {
+        let path: PathBuf = temp_checkpoint("panic");
+        let search: SeedSearch = SeedSearch::new(128, 0, 200).threads(2).limit(1000).checkpoint(&path)
+            .predicate(|candidate| if candidate.seed() == 40 { panic!("predicate failed") } else { Some(1.0) });
+
+        assert!(panic::catch_unwind(AssertUnwindSafe(|| search.run())).is_err());
+
+        //Seed 40 is in the chunk from 32, which was never finished
+        let saved: SearchCheckpoint = SearchCheckpoint::load(&path).unwrap();
+        assert!(saved.next_seed <= 32, "checkpoint moved past the panicked chunk to {}", saved.next_seed);
+
+        let resumed: SearchOutcome = SeedSearch::new(128, 0, 200).threads(2).limit(1000).resume_from(&saved).predicate(|_| Some(1.0)).run().unwrap();
+        let _ = fs::remove_file(&path);
+
+        assert!(resumed.checkpoint.is_finished());
+        assert_eq!(resumed.matches.len(), 200);
+    }
+
+    #[test]
+    fn failing_checkpoint_write_is_an_error () {
+        let path: PathBuf = std::env::temp_dir().join("mc-classic-js-missing-dir").join("checkpoint.txt");
+        let result: Result<SearchOutcome> = SeedSearch::new(128, 0, 20).threads(1).checkpoint(&path).predicate(|_| None).run();
+        assert!(matches!(result, Err(Error::Io(_))));
+    }
+}
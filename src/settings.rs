@@ -0,0 +1,115 @@
+/**
+ * GenerationSettings struct stores the tuning constants of the world generator.
+ * The default is exactly what the game uses, so only change the fields a variant
+ * needs, for example a treeless world with twice the ore:
+ * GenerationSettings { tree_density: 0.0, coal_ore: 180.0, iron_ore: 140.0, gold_ore: 100.0, ..Default::default() }
+ *
+ * distortion_frequency: how tightly the height map noise is sampled, higher gives busier terrain (1.3)
+ * height_scale: multiplies how far the terrain rises above and sinks below the middle of the world,
+ * 0 gives a flat world (1.0)
+ * sea_level: height of the surface of oceans and lakes, None is half the height of the world.
+ * Sand and gravel are only placed at or below it (None)
+ * cave_density: multiplies the number of caves, one per 16384 tiles by default (1.0)
+ * coal_ore, iron_ore, gold_ore: abundance of each ore, which sets how many veins there are and
+ * how long and thick they are (90, 70 and 50)
+ * lava_density: multiplies the number of lava pockets, one try per 10000 tiles by default (1.0)
+ * sand_threshold: noise above this turns beaches into sand, higher gives less sand (8.0)
+ * gravel_threshold: noise above this turns the sea floor into gravel, higher gives less gravel (12.0)
+ * tree_density: multiplies the number of tree clusters, one per 4000 columns by default,
+ * 0 gives a treeless world (1.0)
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GenerationSettings {
+    pub distortion_frequency: f64,
+    pub height_scale: f64,
+    pub sea_level: Option<i32>,
+    pub cave_density: f64,
+    pub coal_ore: f64,
+    pub iron_ore: f64,
+    pub gold_ore: f64,
+    pub lava_density: f64,
+    pub sand_threshold: f64,
+    pub gravel_threshold: f64,
+    pub tree_density: f64
+}
+
+impl GenerationSettings {
+
+    /**
+     * True if every setting is the one the game uses
+     */
+    pub fn is_classic (&self) -> bool {
+        return *self == GenerationSettings::default();
+    }
+
+    /**
+     * Returns the sea level for a world of the given height
+     */
+    pub fn sea_level_for (&self, height: i32) -> i32 {
+        return self.sea_level.unwrap_or(height / 2);
+    }
+}
+
+impl Default for GenerationSettings {
+    fn default () -> Self {
+        GenerationSettings {
+            distortion_frequency: 1.3,
+            height_scale: 1.0,
+            sea_level: None,
+            cave_density: 1.0,
+            coal_ore: 90.0,
+            iron_ore: 70.0,
+            gold_ore: 50.0,
+            lava_density: 1.0,
+            sand_threshold: 8.0,
+            gravel_threshold: 12.0,
+            tree_density: 1.0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::Block;
+    use crate::conformance::Fixture;
+    use crate::generator::ClassicGenerator;
+    use crate::tile_map::TileMap;
+
+    fn generate (settings: GenerationSettings) -> TileMap {
+        return ClassicGenerator::new(7).size(128).settings(settings).generate().unwrap();
+    }
+
+    #[test]
+    fn default_is_the_classic_world () {
+        assert!(GenerationSettings::default().is_classic());
+        assert!(!GenerationSettings { sea_level: Some(32), ..Default::default() }.is_classic());
+        assert!(!GenerationSettings { tree_density: 0.5, ..Default::default() }.is_classic());
+
+        //Checked against the recorded classic output rather than the generator itself
+        let fixtures: Vec<Fixture> = Fixture::parse_all(include_str!("../tests/fixtures/generation_snapshots.txt")).unwrap();
+        let fixture: &Fixture = fixtures.iter().find(|f| (f.world_size, f.seed) == (128, 7)).unwrap();
+        assert!(fixture.mismatched_layers(&generate(GenerationSettings::default())).is_empty());
+    }
+
+    #[test]
+    fn no_tree_density_gives_no_trees () {
+        let world: TileMap = generate(GenerationSettings { tree_density: 0.0, ..Default::default() });
+        assert!(!world.blocks().iter().any(|b| matches!(b, Block::Log | Block::Leaves)));
+    }
+
+    #[test]
+    fn no_ore_density_gives_no_ore () {
+        let world: TileMap = generate(GenerationSettings { coal_ore: 0.0, iron_ore: 0.0, gold_ore: 0.0, ..Default::default() });
+        assert!(!world.blocks().iter().any(|b| matches!(b, Block::CoalOre | Block::IronOre | Block::GoldOre)));
+    }
+
+    #[test]
+    fn sea_level_moves_the_water_surface () {
+        for sea_level in [24, 40] {
+            let world: TileMap = generate(GenerationSettings { sea_level: Some(sea_level), ..Default::default() });
+            let top: Option<i32> = world.positions().filter(|(_, _, _, b)| *b == Block::Water).map(|(_, y, _, _)| y).max();
+            assert_eq!(top, Some(sea_level - 1), "sea level {}", sea_level);
+        }
+    }
+}
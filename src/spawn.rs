@@ -0,0 +1,64 @@
+use crate::block::Block;
+use crate::block_pos::BlockPos;
+use crate::random::Random;
+use crate::tile_map::TileMap;
+
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+
+//Tries before the search gives up, the game then drops the player in below the world
+const SPAWN_TRIES: i32 = 10000;
+
+//Height the game gives a spawn it could not find land for
+pub const NO_SPAWN_Y: i32 = -100;
+
+/**
+ * Finds where a player spawns in a world the way the game does: a random column
+ * within the middle half of the world is picked until the top of it is above the
+ * water level, which is half the height of the world. The top of a column is the
+ * first tile above everything that is not air or liquid, and the spawn is one tile
+ * above that, where the game drops the player in from. If no column is found within
+ * 10000 tries the spawn is at y = NO_SPAWN_Y in the last column tried.
+ *
+ * Like the game, the columns come from an unseeded random, so the same world can
+ * give a different spawn every call. Use find_spawn_with for a repeatable one
+ */
+pub fn find_spawn (tile_map: &TileMap) -> BlockPos {
+    let mut random: Random = Random::new(RandomState::new().hash_one(0u8) as i64);
+    return find_spawn_with(tile_map, &mut random);
+}
+
+/**
+ * Same as find_spawn, but the columns are drawn from random, so the
+ * same tile map and random state always give the same spawn
+ */
+pub fn find_spawn_with (tile_map: &TileMap, random: &mut Random) -> BlockPos {
+    let water_level: i32 = tile_map.height() / 2;
+
+    let mut tries: i32 = 0;
+    loop {
+        tries += 1;
+        let x: i32 = random.next_int(tile_map.width() / 2) + tile_map.width() / 4;
+        let z: i32 = random.next_int(tile_map.depth() / 2) + tile_map.depth() / 4;
+        let y: i32 = highest_tile(tile_map, x, z) + 1;
+
+        if tries == SPAWN_TRIES {
+            return BlockPos::new(x, NO_SPAWN_Y, z);
+        }
+        if y > water_level {
+            return BlockPos::new(x, y, z);
+        }
+    }
+}
+
+/**
+ * Returns the first y above every tile in a column that is not air or liquid,
+ * 0 if the column is empty, the same as the game's getHighestTile
+ */
+pub fn highest_tile (tile_map: &TileMap, x: i32, z: i32) -> i32 {
+    let mut y: i32 = tile_map.height();
+    while y > 0 && tile_map.get(x, y - 1, z).is_none_or(|b| b == Block::Air || b.is_liquid()) {
+        y -= 1;
+    }
+    return y;
+}
@@ -0,0 +1,221 @@
+use crate::block::Block;
+use crate::error::{Error, Result};
+
+/**
+ * TileMap struct stores every block of a world along with
+ * its dimensions:
+ * width: size along the x axis
+ * height: size along the y axis, classic js worlds are always 64 high
+ * depth: size along the z axis
+ *
+ * Blocks are stored in the same flat layout that get_tile_map returns,
+ * X fastest, then Z, then Y. So [0] is X:0, Y:0, Z:0, [1] is X:1, Y:0, Z:0
+ * and the index of any block is (y * depth + z) * width + x
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TileMap {
+    width: i32,
+    height: i32,
+    depth: i32,
+    tiles: Vec<Block>
+}
+
+impl TileMap {
+
+    /**
+     * Creates a tile map of the given dimensions filled with air
+     */
+    pub fn new (width: i32, height: i32, depth: i32) -> Self {
+        let volume: usize = width.max(0) as usize * height.max(0) as usize * depth.max(0) as usize;
+        TileMap { width: width.max(0), height: height.max(0), depth: depth.max(0), tiles: vec![Block::Air; volume] }
+    }
+
+    /**
+     * Creates a tile map from the flat layout returned by get_tile_map.
+     * Fails if the length of tiles does not match the dimensions
+     * or if a tile is not a known block id
+     */
+    pub fn from_flat (width: i32, height: i32, depth: i32, tiles: &[u8]) -> Result<Self> {
+        let expected: usize = width.max(0) as usize * height.max(0) as usize * depth.max(0) as usize;
+        if width < 0 || height < 0 || depth < 0 || tiles.len() != expected {
+            return Err(Error::InvalidTileMap { expected, found: tiles.len() });
+        }
+
+        let mut blocks: Vec<Block> = Vec::with_capacity(tiles.len());
+        for id in tiles {
+            blocks.push(Block::try_from(*id)?);
+        }
+
+        return Ok(TileMap { width, height, depth, tiles: blocks });
+    }
+
+    /**
+     * Converts the tile map back into the flat layout of block ids
+     */
+    pub fn to_flat (&self) -> Vec<u8> {
+        return self.tiles.iter().map(|b| b.id()).collect();
+    }
+
+    pub fn width (&self) -> i32 { self.width }
+    pub fn height (&self) -> i32 { self.height }
+    pub fn depth (&self) -> i32 { self.depth }
+
+    /**
+     * Returns the index of a position in the flat layout,
+     * or None if the position is outside of the map
+     */
+    pub fn index (&self, x: i32, y: i32, z: i32) -> Option<usize> {
+        if !self.contains(x, y, z) { return None; }
+        return Some(((y * self.depth + z) * self.width + x) as usize);
+    }
+
+    /**
+     * True if the position is inside of the map
+     */
+    pub fn contains (&self, x: i32, y: i32, z: i32) -> bool {
+        return x >= 0 && y >= 0 && z >= 0 && x < self.width && y < self.height && z < self.depth;
+    }
+
+    /**
+     * Returns the block at a position, or None if the
+     * position is outside of the map
+     */
+    pub fn get (&self, x: i32, y: i32, z: i32) -> Option<Block> {
+        return self.index(x, y, z).map(|i| self.tiles[i]);
+    }
+
+    /**
+     * Sets the block at a position, returns false and leaves
+     * the map unchanged if the position is outside of the map
+     */
+    pub fn set (&mut self, x: i32, y: i32, z: i32, block: Block) -> bool {
+        match self.index(x, y, z) {
+            Some(i) => { self.tiles[i] = block; true },
+            None => false
+        }
+    }
+
+    /**
+     * Returns all blocks in the flat layout
+     */
+    pub fn blocks (&self) -> &[Block] {
+        return &self.tiles;
+    }
+
+    pub fn blocks_mut (&mut self) -> &mut [Block] {
+        return &mut self.tiles;
+    }
+
+    /**
+     * Iterates over every horizontal layer from y = 0 upwards.
+     * Each layer is a slice of width * depth blocks in X, then Z order
+     */
+    pub fn layers (&self) -> impl Iterator<Item = &[Block]> + '_ {
+        let layer_size: usize = (self.width * self.depth).max(1) as usize;
+        return self.tiles.chunks(layer_size);
+    }
+
+    /**
+     * Iterates over every vertical column, X fastest then Z
+     */
+    pub fn columns (&self) -> impl Iterator<Item = Column<'_>> + '_ {
+        return (0..self.depth).flat_map(move |z| (0..self.width).map(move |x| Column { tile_map: self, x, z }));
+    }
+
+    /**
+     * Iterates over every position and its block as (x, y, z, block)
+     * in the flat layout order
+     */
+    pub fn positions (&self) -> impl Iterator<Item = (i32, i32, i32, Block)> + '_ {
+        let width: i32 = self.width;
+        let depth: i32 = self.depth;
+        return self.tiles.iter().enumerate().map(move |(i, block)| {
+            let i: i32 = i as i32;
+            (i % width, i / (width * depth), (i / width) % depth, *block)
+        });
+    }
+}
+
+impl From<TileMap> for Vec<u8> {
+    fn from (tile_map: TileMap) -> Vec<u8> {
+        return tile_map.to_flat();
+    }
+}
+
+/**
+ * Column struct is a single vertical column of a tile map at x, z
+ */
+#[derive(Clone, Copy, Debug)]
+pub struct Column<'a> {
+    tile_map: &'a TileMap,
+    pub x: i32,
+    pub z: i32
+}
+
+impl<'a> Column<'a> {
+
+    /**
+     * Returns the block at height y, or None if y is outside of the map
+     */
+    pub fn get (&self, y: i32) -> Option<Block> {
+        return self.tile_map.get(self.x, y, self.z);
+    }
+
+    /**
+     * Iterates over the blocks of the column from y = 0 upwards
+     */
+    pub fn blocks (&self) -> impl Iterator<Item = Block> + 'a {
+        let column: Column<'a> = *self;
+        return (0..self.tile_map.height).filter_map(move |y| column.get(y));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn outside_of_the_map_is_none () {
+        let mut tile_map: TileMap = TileMap::new(2, 3, 4);
+
+        assert!(tile_map.set(1, 2, 3, Block::Glass));
+        assert_eq!(tile_map.get(1, 2, 3), Some(Block::Glass));
+
+        for (x, y, z) in [(2, 0, 0), (0, 3, 0), (0, 0, 4), (-1, 0, 0), (0, -1, 0), (0, 0, -1)] {
+            assert_eq!(tile_map.get(x, y, z), None);
+            assert!(!tile_map.set(x, y, z, Block::Stone));
+        }
+        assert_eq!(tile_map.blocks().iter().filter(|b| **b != Block::Air).count(), 1);
+    }
+
+    #[test]
+    fn from_flat_checks_the_length () {
+        assert!(matches!(TileMap::from_flat(2, 3, 4, &[0; 23]), Err(Error::InvalidTileMap { expected: 24, found: 23 })));
+        assert!(matches!(TileMap::from_flat(2, 3, 4, &[0; 25]), Err(Error::InvalidTileMap { expected: 24, found: 25 })));
+        assert!(matches!(TileMap::from_flat(2, 3, 4, &[47; 24]), Err(Error::UnknownBlock(47))));
+
+        let tiles: Vec<u8> = (0..24).collect();
+        assert_eq!(TileMap::from_flat(2, 3, 4, &tiles).unwrap().to_flat(), tiles);
+    }
+
+    #[test]
+    fn iterates_x_fastest_then_z_then_y () {
+        let tiles: Vec<u8> = (0..24).collect();
+        let tile_map: TileMap = TileMap::from_flat(2, 3, 4, &tiles).unwrap();
+
+        let positions: Vec<(i32, i32, i32)> = tile_map.positions().map(|(x, y, z, _)| (x, y, z)).collect();
+        assert_eq!(&positions[..5], &[(0, 0, 0), (1, 0, 0), (0, 0, 1), (1, 0, 1), (0, 0, 2)]);
+        assert_eq!(positions[8], (0, 1, 0));
+        for (x, y, z, block) in tile_map.positions() {
+            assert_eq!(Some(block), tile_map.get(x, y, z));
+        }
+
+        let layers: Vec<&[Block]> = tile_map.layers().collect();
+        assert_eq!(layers.len(), 3);
+        assert_eq!(layers[1].iter().map(|b| b.id()).collect::<Vec<u8>>(), (8..16).collect::<Vec<u8>>());
+
+        let columns: Vec<(i32, i32)> = tile_map.columns().map(|column| (column.x, column.z)).collect();
+        assert_eq!(columns, vec![(0, 0), (1, 0), (0, 1), (1, 1), (0, 2), (1, 2), (0, 3), (1, 3)]);
+        assert_eq!(tile_map.columns().nth(3).unwrap().blocks().map(|b| b.id()).collect::<Vec<u8>>(), vec![3, 11, 19]);
+    }
+}
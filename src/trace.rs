@@ -0,0 +1,173 @@
+use crate::block::Block;
+use crate::block_pos::BlockPos;
+
+/**
+ * GenerationTrace struct stores the intermediate results of world generation
+ * that the final tile map no longer shows, grouped by the stage that made them.
+ * Column values are laid out x + z * width, the same as the generator's height maps
+ *
+ * Raising:
+ * raw_heights: height of every column from the noise, relative to the middle of the world,
+ * height / 2, whatever sea_level is set to
+ * Eroding:
+ * eroded_heights: raw_heights after terracing
+ * Soiling:
+ * dirt_heights: every column is filled with dirt up to this height
+ * rock_heights: every column is filled with stone up to this height, over the top of the dirt
+ * surface_heights: the higher of the two, which Growing and Planting use as the ground
+ * Carving:
+ * carved: one flag per tile in the tile map layout, true where a cave turned stone into air
+ * ore_veins: every vein placed, coal first, then iron, then gold
+ * Watering:
+ * water_bodies: every flood fill of water that placed any tiles
+ * Melting:
+ * lava_pockets: every flood fill of lava that placed any tiles
+ * Planting:
+ * trees: every tree planted
+ */
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GenerationTrace {
+    width: i32,
+    height: i32,
+    depth: i32,
+    pub raw_heights: Vec<f64>,
+    pub eroded_heights: Vec<f64>,
+    pub dirt_heights: Vec<f64>,
+    pub rock_heights: Vec<f64>,
+    pub surface_heights: Vec<f64>,
+    pub carved: Vec<bool>,
+    pub ore_veins: Vec<OreVein>,
+    pub water_bodies: Vec<FloodFill>,
+    pub lava_pockets: Vec<FloodFill>,
+    pub trees: Vec<Tree>
+}
+
+impl GenerationTrace {
+
+    /**
+     * Creates an empty trace for a world of the given dimensions
+     */
+    pub fn new (width: i32, height: i32, depth: i32) -> Self {
+        let volume: usize = width.max(0) as usize * height.max(0) as usize * depth.max(0) as usize;
+        GenerationTrace { width: width.max(0), height: height.max(0), depth: depth.max(0), carved: vec![false; volume], ..Default::default() }
+    }
+
+    pub fn width (&self) -> i32 { self.width }
+    pub fn height (&self) -> i32 { self.height }
+    pub fn depth (&self) -> i32 { self.depth }
+
+    /**
+     * Returns the index of a column in the height maps,
+     * or None if the column is outside of the world
+     */
+    pub fn column_index (&self, x: i32, z: i32) -> Option<usize> {
+        if x < 0 || z < 0 || x >= self.width || z >= self.depth { return None; }
+        return Some((x + z * self.width) as usize);
+    }
+
+    /**
+     * True if a cave carved the tile at a position
+     */
+    pub fn is_carved (&self, x: i32, y: i32, z: i32) -> bool {
+        if x < 0 || y < 0 || z < 0 || x >= self.width || y >= self.height || z >= self.depth { return false; }
+        return self.carved[((y * self.depth + z) * self.width + x) as usize];
+    }
+}
+
+/**
+ * OreVein struct is a single vein of ore
+ * block: the ore placed
+ * path: the centre and radius of the vein at every step, in the order they were placed
+ * tiles: number of stone tiles the vein turned into ore
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct OreVein {
+    pub block: Block,
+    pub path: Vec<VeinStep>,
+    pub tiles: usize
+}
+
+/**
+ * VeinStep struct is one step along an ore vein, a sphere
+ * of ore squashed to half height around x, y, z
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VeinStep {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+    pub radius: f64
+}
+
+/**
+ * FloodFill struct is a single body of water or lava
+ * start: the tile the fill started from
+ * tiles: number of tiles the fill placed
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FloodFill {
+    pub start: BlockPos,
+    pub tiles: usize
+}
+
+/**
+ * Tree struct is a single tree
+ * pos: the lowest log of the trunk, the dirt it stands on is one below
+ * height: number of logs in the trunk, 4 to 6
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Tree {
+    pub pos: BlockPos,
+    pub height: i32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::ClassicGenerator;
+    use crate::pipeline::{classic_stages, GenerationStage};
+    use crate::tile_map::TileMap;
+
+    use std::sync::Arc;
+
+    //The world and its trace as Carving left them
+    fn carved (seed: i64) -> (TileMap, GenerationTrace) {
+        let stages: Vec<Arc<dyn GenerationStage>> = classic_stages()[..4].to_vec();
+        return ClassicGenerator::new(seed).size(128).stages(stages).generate_traced().unwrap();
+    }
+
+    #[test]
+    fn trees_stand_on_logs () {
+        let (world, trace): (TileMap, GenerationTrace) = ClassicGenerator::new(7).size(128).generate_traced().unwrap();
+        assert!(!trace.trees.is_empty());
+
+        for tree in &trace.trees {
+            assert_eq!(world.get(tree.pos.x, tree.pos.y, tree.pos.z), Some(Block::Log), "tree at {}", tree.pos);
+            assert!((4..=6).contains(&tree.height));
+        }
+    }
+
+    #[test]
+    fn carved_tiles_are_air_after_carving () {
+        let (world, trace): (TileMap, GenerationTrace) = carved(7);
+        assert!(trace.carved.iter().any(|carved| *carved));
+
+        for (x, y, z, block) in world.positions() {
+            if trace.is_carved(x, y, z) {
+                assert_eq!(block, Block::Air, "carved tile at {} {} {}", x, y, z);
+            }
+        }
+    }
+
+    #[test]
+    fn ore_veins_count_the_ore_placed () {
+        let (world, trace): (TileMap, GenerationTrace) = carved(7);
+
+        for ore in [Block::CoalOre, Block::IronOre, Block::GoldOre] {
+            let traced: usize = trace.ore_veins.iter().filter(|vein| vein.block == ore).map(|vein| vein.tiles).sum();
+            let placed: usize = world.blocks().iter().filter(|block| **block == ore).count();
+            assert!(placed > 0);
+            assert_eq!(traced, placed, "{}", ore);
+        }
+    }
+}
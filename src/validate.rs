@@ -0,0 +1,353 @@
+use crate::block::Block;
+use crate::block_pos::BlockPos;
+use crate::error::{Error, Result};
+use crate::tile_map::TileMap;
+use crate::world_generator::WorldGenerator;
+use crate::{generate_tile_map, ChangedBlockMap, ChangedBlocks, JSLevel};
+
+use indexmap::IndexMap;
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use std::fmt;
+
+/**
+ * ValidationIssue enum stores a single problem found in a level
+ * InvalidWorldSize: worldSize is not 128, 256 or 512, nothing else can be checked
+ * MalformedKey: a changedBlocks key is not in the p{x}_{y}_{z} format
+ * MalformedEntry: a changedBlocks value is not an object of type {a: u8, bt: u8}
+ * OutOfBounds: a changed block is outside of the world
+ * UnknownBlock: bt is not a block id the game knows, the entry is left out of the level
+ * InvalidAction: a is neither 0 nor 1
+ * MismatchedAction: a says the block matches natural generation when it does not, or the other way around
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ValidationIssue {
+    InvalidWorldSize(i32),
+    MalformedKey { key: String },
+    MalformedEntry { key: String },
+    OutOfBounds { pos: BlockPos },
+    UnknownBlock { pos: BlockPos, bt: u8 },
+    InvalidAction { pos: BlockPos, a: u8 },
+    MismatchedAction { pos: BlockPos, a: u8, expected: u8 }
+}
+
+impl ValidationIssue {
+
+    /**
+     * Returns the position the issue was found at, if there is one
+     */
+    pub fn pos (&self) -> Option<BlockPos> {
+        match self {
+            ValidationIssue::OutOfBounds { pos } |
+            ValidationIssue::UnknownBlock { pos, .. } |
+            ValidationIssue::InvalidAction { pos, .. } |
+            ValidationIssue::MismatchedAction { pos, .. } => Some(*pos),
+            _ => None
+        }
+    }
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt (&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationIssue::InvalidWorldSize(size) => write!(f, "world size {} is not 128, 256 or 512", size),
+            ValidationIssue::MalformedKey { key } => write!(f, "changed block key {:?} is not in the p{{x}}_{{y}}_{{z}} format", key),
+            ValidationIssue::MalformedEntry { key } => write!(f, "changed block {:?} is not of type {{a, bt}}", key),
+            ValidationIssue::OutOfBounds { pos } => write!(f, "{} is outside of the world", pos),
+            ValidationIssue::UnknownBlock { pos, bt } => write!(f, "{} has unknown block id {}", pos, bt),
+            ValidationIssue::InvalidAction { pos, a } => write!(f, "{} has a = {}, must be 0 or 1", pos, a),
+            ValidationIssue::MismatchedAction { pos, a, expected } => write!(f, "{} has a = {}, natural generation says {}", pos, a, expected)
+        }
+    }
+}
+
+impl JSLevel {
+
+    /**
+     * Checks the level and returns every issue found, an empty list means the
+     * level is safe to load. Checking a against natural generation requires
+     * generating the world, so this takes as long as generate_tile_map. A world
+     * size the generator rejects is an issue, any other error from it is returned
+     */
+    pub fn validate (&self) -> Result<Vec<ValidationIssue>> {
+        return Ok(self.check(None)?.0);
+    }
+
+    /**
+     * Same as validate, but a is checked against what generator makes
+     * for the seed instead of natural generation
+     */
+    pub fn validate_with (&self, generator: &dyn WorldGenerator) -> Result<Vec<ValidationIssue>> {
+        return Ok(self.check(Some(generator))?.0);
+    }
+
+    /**
+     * Same as validate, but also fixes what it can. Entries that are out of
+     * bounds are dropped, and a is set to match natural
+     * generation. Returns every issue found, an invalid world size is the
+     * only one left in the level afterwards
+     */
+    pub fn repair (&mut self) -> Result<Vec<ValidationIssue>> {
+        return self.repair_from(None);
+    }
+
+    /**
+     * Same as repair, but a is set to match what generator makes
+     * for the seed instead of natural generation
+     */
+    pub fn repair_with (&mut self, generator: &dyn WorldGenerator) -> Result<Vec<ValidationIssue>> {
+        return self.repair_from(Some(generator));
+    }
+
+    fn repair_from (&mut self, generator: Option<&dyn WorldGenerator>) -> Result<Vec<ValidationIssue>> {
+        let (issues, natural) = self.check(generator)?;
+        let natural: TileMap = match natural {
+            Some(natural) => natural,
+            None => return Ok(issues)
+        };
+
+        for issue in &issues {
+            match issue {
+                ValidationIssue::OutOfBounds { pos } => {
+                    self.changedBlocks.shift_remove(pos);
+                },
+                ValidationIssue::InvalidAction { pos, .. } | ValidationIssue::MismatchedAction { pos, .. } => {
+                    if let Some(changed) = self.changedBlocks.get_mut(pos) {
+                        changed.a = expected_action(&natural, *pos, changed.bt);
+                    }
+                },
+                _ => ()
+            }
+        }
+
+        return Ok(issues);
+    }
+
+    //Shared by validate and repair, also returns natural generation so repair can reuse it.
+    //Without a generator the world is classic, which only comes in the sizes the game offers
+    fn check (&self, generator: Option<&dyn WorldGenerator>) -> Result<(Vec<ValidationIssue>, Option<TileMap>)> {
+        let mut issues: Vec<ValidationIssue> = Vec::new();
+
+        let generated: Result<TileMap> = match generator {
+            Some(generator) => generator.generate_for_size(self.worldSize, self.worldSeed),
+            None => generate_tile_map(self.worldSize, self.worldSeed)
+        };
+
+        let natural: TileMap = match generated {
+            Ok(natural) => natural,
+            Err(Error::InvalidWorldSize(_) | Error::InvalidDimensions { .. }) => {
+                issues.push(ValidationIssue::InvalidWorldSize(self.worldSize));
+                return Ok((issues, None));
+            },
+            Err(e) => return Err(e)
+        };
+
+        for (pos, changed) in &self.changedBlocks {
+            let pos: BlockPos = *pos;
+
+            if !natural.contains(pos.x, pos.y, pos.z) {
+                issues.push(ValidationIssue::OutOfBounds { pos });
+                continue;
+            }
+
+            let expected: u8 = expected_action(&natural, pos, changed.bt);
+            if changed.a > 1 {
+                issues.push(ValidationIssue::InvalidAction { pos, a: changed.a });
+            } else if changed.a != expected {
+                issues.push(ValidationIssue::MismatchedAction { pos, a: changed.a, expected });
+            }
+        }
+
+        return Ok((issues, Some(natural)));
+    }
+}
+
+//a is 1 if bt differs from the naturally generated block at the position
+fn expected_action (natural: &TileMap, pos: BlockPos, bt: Block) -> u8 {
+    return (natural.get(pos.x, pos.y, pos.z) != Some(bt)) as u8;
+}
+
+//savedGame object with changedBlocks left unparsed, so bad entries can be reported one by one
+#[allow(non_snake_case)]
+#[derive(Deserialize)]
+struct RawLevel {
+    worldSeed: i64,
+    #[serde(default)]
+    changedBlocks: IndexMap<String, Value>,
+    worldSize: i32,
+    version: u8,
+    #[serde(flatten)]
+    extra: IndexMap<String, Value>
+}
+
+//changedBlocks value with bt still a raw id, so an unknown block can be told apart from a malformed entry
+#[derive(Deserialize)]
+struct RawChangedBlocks {
+    a: u8,
+    bt: u8
+}
+
+/**
+ * Lenient version of deserialize_saved_game for saves from untrusted sources.
+ * Instead of failing on the first malformed changedBlocks key, value or unknown
+ * block id, bad entries are left out of the level and returned as issues. The json still has
+ * to be an object with worldSeed, worldSize and version. Run validate or repair
+ * on the returned level to check the entries that did parse
+ */
+pub fn deserialize_saved_game_lenient (json_string: &str) -> Result<(JSLevel, Vec<ValidationIssue>)> {
+    let raw: RawLevel = serde_json::from_str(json_string)?;
+
+    let mut issues: Vec<ValidationIssue> = Vec::new();
+    let mut changed_blocks: ChangedBlockMap = ChangedBlockMap::new();
+
+    for (key, value) in raw.changedBlocks {
+        let pos: BlockPos = match key.parse() {
+            Ok(pos) => pos,
+            Err(_) => {
+                issues.push(ValidationIssue::MalformedKey { key });
+                continue;
+            }
+        };
+
+        let raw: RawChangedBlocks = match serde_json::from_value(value) {
+            Ok(raw) => raw,
+            Err(_) => {
+                issues.push(ValidationIssue::MalformedEntry { key });
+                continue;
+            }
+        };
+
+        match Block::from_id(raw.bt) {
+            Some(bt) => { changed_blocks.insert(pos, ChangedBlocks { a: raw.a, bt }); },
+            None => issues.push(ValidationIssue::UnknownBlock { pos, bt: raw.bt })
+        }
+    }
+
+    let mut level: JSLevel = JSLevel::new(raw.worldSeed, changed_blocks, raw.worldSize, raw.version);
+    level.extra = raw.extra;
+    return Ok((level, issues));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world_generator::FlatGenerator;
+
+    //28 stone, 3 dirt and grass at y = 31, quick to generate
+    fn flat () -> FlatGenerator {
+        return FlatGenerator::default();
+    }
+
+    fn level (entries: &[(BlockPos, u8, Block)]) -> JSLevel {
+        let mut changed_blocks: ChangedBlockMap = ChangedBlockMap::new();
+        for (pos, a, bt) in entries {
+            changed_blocks.insert(*pos, ChangedBlocks { a: *a, bt: *bt });
+        }
+        return JSLevel::new(1, changed_blocks, 128, 1);
+    }
+
+    #[test]
+    fn valid_level_has_no_issues () {
+        let level: JSLevel = level(&[(BlockPos::new(0, 31, 0), 1, Block::Glass), (BlockPos::new(3, 10, 3), 0, Block::Stone)]);
+        assert!(level.validate_with(&flat()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn reports_every_kind_of_bad_entry () {
+        let level: JSLevel = level(&[
+            (BlockPos::new(128, 0, 0), 1, Block::Stone),
+            (BlockPos::new(0, 64, 0), 1, Block::Stone),
+            (BlockPos::new(2, 2, 2), 7, Block::Glass),
+            (BlockPos::new(3, 3, 3), 1, Block::Stone),
+            (BlockPos::new(4, 40, 4), 0, Block::Glass)
+        ]);
+
+        assert_eq!(level.validate_with(&flat()).unwrap(), vec![
+            ValidationIssue::OutOfBounds { pos: BlockPos::new(128, 0, 0) },
+            ValidationIssue::OutOfBounds { pos: BlockPos::new(0, 64, 0) },
+            ValidationIssue::InvalidAction { pos: BlockPos::new(2, 2, 2), a: 7 },
+            ValidationIssue::MismatchedAction { pos: BlockPos::new(3, 3, 3), a: 1, expected: 0 },
+            ValidationIssue::MismatchedAction { pos: BlockPos::new(4, 40, 4), a: 0, expected: 1 }
+        ]);
+    }
+
+    #[test]
+    fn repair_fixes_what_validate_reports () {
+        let mut level: JSLevel = level(&[
+            (BlockPos::new(0, 0, 0), 1, Block::Glass),
+            (BlockPos::new(128, 0, 0), 1, Block::Stone),
+            (BlockPos::new(2, 2, 2), 7, Block::Glass),
+            (BlockPos::new(3, 3, 3), 1, Block::Stone)
+        ]);
+
+        assert_eq!(level.repair_with(&flat()).unwrap().len(), 3);
+        assert!(level.validate_with(&flat()).unwrap().is_empty());
+
+        //Dropped entries are gone and the rest keep their order
+        let expected: Vec<(BlockPos, ChangedBlocks)> = vec![
+            (BlockPos::new(0, 0, 0), ChangedBlocks::new(1, Block::Glass)),
+            (BlockPos::new(2, 2, 2), ChangedBlocks::new(1, Block::Glass)),
+            (BlockPos::new(3, 3, 3), ChangedBlocks::new(0, Block::Stone))
+        ];
+        assert_eq!(level.changedBlocks.into_iter().collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn invalid_world_size_stops_the_check () {
+        let mut level: JSLevel = level(&[(BlockPos::new(0, 0, 0), 5, Block::Glass)]);
+        level.worldSize = 100;
+
+        assert_eq!(level.validate().unwrap(), vec![ValidationIssue::InvalidWorldSize(100)]);
+        assert_eq!(level.repair().unwrap(), vec![ValidationIssue::InvalidWorldSize(100)]);
+        assert_eq!(level.changedBlocks.len(), 1);
+    }
+
+    #[test]
+    fn generator_errors_are_not_world_size_issues () {
+        struct Cancelled;
+        impl WorldGenerator for Cancelled {
+            fn generate_world (&self, _: i32, _: i32, _: i32, _: i64) -> Result<TileMap> {
+                return Err(Error::Cancelled);
+            }
+        }
+
+        let mut level: JSLevel = level(&[(BlockPos::new(0, 0, 0), 5, Block::Glass)]);
+        assert!(matches!(level.validate_with(&Cancelled), Err(Error::Cancelled)));
+        assert!(matches!(level.repair_with(&Cancelled), Err(Error::Cancelled)));
+        assert_eq!(level.changedBlocks.len(), 1);
+
+        //Custom generators reject dimensions of their own
+        level.worldSize = 2000;
+        assert_eq!(level.validate_with(&flat()).unwrap(), vec![ValidationIssue::InvalidWorldSize(2000)]);
+    }
+
+    #[test]
+    fn lenient_deserializer_skips_bad_entries () {
+        let json: &str = r#"{"worldSeed":5,"changedBlocks":{"p1_2_3":{"a":1,"bt":9},"p01_2_3":{"a":1,"bt":9},"q1_2_3":{"a":1,"bt":9},"p4_5_6":{"a":1},"p7_8_9":{"a":300,"bt":1},"p0_0_0":{"a":0,"bt":2},"p9_9_9":{"a":1,"bt":200}},"worldSize":128,"version":1,"name":"kept"}"#;
+
+        let (level, issues): (JSLevel, Vec<ValidationIssue>) = deserialize_saved_game_lenient(json).unwrap();
+
+        assert_eq!(issues, vec![
+            ValidationIssue::MalformedKey { key: "p01_2_3".to_string() },
+            ValidationIssue::MalformedKey { key: "q1_2_3".to_string() },
+            ValidationIssue::MalformedEntry { key: "p4_5_6".to_string() },
+            ValidationIssue::MalformedEntry { key: "p7_8_9".to_string() },
+            ValidationIssue::UnknownBlock { pos: BlockPos::new(9, 9, 9), bt: 200 }
+        ]);
+        assert_eq!(level.changedBlocks.keys().copied().collect::<Vec<_>>(), vec![BlockPos::new(1, 2, 3), BlockPos::new(0, 0, 0)]);
+        assert_eq!((level.worldSeed, level.worldSize, level.version), (5, 128, 1));
+        assert_eq!(level.extra["name"], Value::from("kept"));
+    }
+
+    #[test]
+    fn lenient_deserializer_still_needs_the_level_keys () {
+        assert!(matches!(deserialize_saved_game_lenient(r#"{"changedBlocks":{},"worldSize":128,"version":1}"#), Err(Error::Json(_))));
+        assert!(matches!(deserialize_saved_game_lenient("not json"), Err(Error::Json(_))));
+
+        //The strict deserializer fails on the first bad entry instead
+        assert!(crate::deserialize_saved_game(r#"{"worldSeed":5,"changedBlocks":{"p01_2_3":{"a":1,"bt":9}},"worldSize":128,"version":1}"#.to_string()).is_err());
+        assert!(crate::deserialize_saved_game(r#"{"worldSeed":5,"changedBlocks":{"p1_2_3":{"a":1,"bt":200}},"worldSize":128,"version":1}"#.to_string()).is_err());
+    }
+}
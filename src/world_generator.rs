@@ -0,0 +1,263 @@
+use crate::block::Block;
+use crate::error::Result;
+use crate::generator::check_dimensions;
+use crate::random::Random;
+use crate::tile_map::TileMap;
+use crate::WORLD_HEIGHT;
+
+use std::f64::consts::PI;
+
+/**
+ * WorldGenerator trait is anything that can build a world from its dimensions
+ * and a seed. Every api that compares a world against natural generation, such
+ * as JSLevel::from_tile_map_with or write_saved_game_json_with, accepts one as
+ * the baseline, so custom maps get the same changedBlocks handling as classic ones.
+ * The built in generators are:
+ * ClassicGenerator: the game's own world generator, see its WorldGenerator impl
+ * FlatGenerator: layers of blocks across the whole world
+ * VoidGenerator: nothing but air
+ * IslandGenerator: a single island in an ocean
+ */
+pub trait WorldGenerator {
+
+    /**
+     * Generates a world of the given dimensions for a seed. Generators that do
+     * not use the seed must still return the same world for any seed
+     */
+    fn generate_world (&self, width: i32, height: i32, depth: i32, seed: i64) -> Result<TileMap>;
+
+    /**
+     * Generates a world the way a savedGame describes one, worldSize
+     * wide and deep and as high as every classic js world
+     */
+    fn generate_for_size (&self, world_size: i32, seed: i64) -> Result<TileMap> {
+        return self.generate_world(world_size, WORLD_HEIGHT, world_size, seed);
+    }
+}
+
+/**
+ * FlatGenerator struct fills the world with layers of blocks from y = 0 upwards,
+ * layers that do not fit in the height of the world are cut off
+ * layers: each block and how many tiles thick its layer is, bottom first
+ *
+ * The default is 28 stone, 3 dirt and 1 grass, so the grass is at y = 31
+ * like the shore of a classic world. Build other worlds with layer, for example
+ * FlatGenerator::new().layer(Block::Stone, 1).layer(Block::Sand, 10)
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FlatGenerator {
+    pub layers: Vec<(Block, i32)>
+}
+
+impl FlatGenerator {
+
+    //Creates a generator without any layers, a world of air until layers are added
+    pub fn new () -> Self {
+        FlatGenerator { layers: Vec::new() }
+    }
+
+    /**
+     * Adds a layer of a block above the layers added so far
+     */
+    pub fn layer (mut self, block: Block, thickness: i32) -> Self {
+        self.layers.push((block, thickness));
+        return self;
+    }
+}
+
+impl Default for FlatGenerator {
+    fn default () -> Self {
+        FlatGenerator::new().layer(Block::Stone, 28).layer(Block::Dirt, 3).layer(Block::Grass, 1)
+    }
+}
+
+impl WorldGenerator for FlatGenerator {
+    fn generate_world (&self, width: i32, height: i32, depth: i32, _seed: i64) -> Result<TileMap> {
+        check_dimensions(width, height, depth)?;
+        let mut tile_map: TileMap = TileMap::new(width, height, depth);
+
+        //Every layer of the world is width * depth tiles, so a flat layer is a run of tiles
+        let layer_size: usize = (width * depth) as usize;
+        let mut y: i32 = 0;
+        for (block, thickness) in &self.layers {
+            let top: i32 = (y + (*thickness).max(0)).min(height);
+            tile_map.blocks_mut()[y as usize * layer_size..top as usize * layer_size].fill(*block);
+            y = top;
+        }
+
+        return Ok(tile_map);
+    }
+}
+
+/**
+ * VoidGenerator struct generates a world of nothing but air
+ */
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct VoidGenerator;
+
+impl WorldGenerator for VoidGenerator {
+    fn generate_world (&self, width: i32, height: i32, depth: i32, _seed: i64) -> Result<TileMap> {
+        check_dimensions(width, height, depth)?;
+        return Ok(TileMap::new(width, height, depth));
+    }
+}
+
+/**
+ * IslandGenerator struct generates a single island in the middle of an ocean.
+ * The seed decides the shape of the coastline, the same seed always gives the same island
+ * radius: radius of the island as a fraction of the smaller of the width and depth (0.35)
+ * peak: height of the middle of the island above the sea (12)
+ * sea_level: height of the surface of the ocean, None is half the height of the world (None)
+ *
+ * The island is grass over dirt over stone with sand along the shore,
+ * and the sea floor slopes down from the shore to 8 tiles below the sea
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct IslandGenerator {
+    pub radius: f64,
+    pub peak: i32,
+    pub sea_level: Option<i32>
+}
+
+impl IslandGenerator {
+
+    //Number of bumps of the coastline, each with its own size and position
+    const HARMONICS: usize = 4;
+
+    pub fn new () -> Self {
+        return IslandGenerator::default();
+    }
+
+    pub fn radius (mut self, radius: f64) -> Self {
+        self.radius = radius;
+        return self;
+    }
+
+    pub fn peak (mut self, peak: i32) -> Self {
+        self.peak = peak;
+        return self;
+    }
+
+    pub fn sea_level (mut self, sea_level: i32) -> Self {
+        self.sea_level = Some(sea_level);
+        return self;
+    }
+}
+
+impl Default for IslandGenerator {
+    fn default () -> Self {
+        IslandGenerator { radius: 0.35, peak: 12, sea_level: None }
+    }
+}
+
+impl WorldGenerator for IslandGenerator {
+    fn generate_world (&self, width: i32, height: i32, depth: i32, seed: i64) -> Result<TileMap> {
+        check_dimensions(width, height, depth)?;
+        let mut tile_map: TileMap = TileMap::new(width, height, depth);
+
+        let sea_level: i32 = self.sea_level.unwrap_or(height / 2);
+        let radius: f64 = self.radius * width.min(depth) as f64;
+        let (centre_x, centre_z): (f64, f64) = (width as f64 / 2.0, depth as f64 / 2.0);
+
+        //The coastline is a circle with a few bumps, each bump is a sine wave around the island
+        let mut random: Random = Random::new(seed);
+        let harmonics: Vec<(f64, f64)> = (0..Self::HARMONICS).map(|_| (random.next_float() * 0.12, random.next_float() * PI * 2.0)).collect();
+
+        for x in 0..width {
+            for z in 0..depth {
+                let (dx, dz): (f64, f64) = (x as f64 + 0.5 - centre_x, z as f64 + 0.5 - centre_z);
+                let angle: f64 = dz.atan2(dx);
+
+                let mut shore: f64 = radius;
+                for (k, (size, phase)) in harmonics.iter().enumerate() {
+                    shore *= 1.0 + size * ((k + 2) as f64 * angle + phase).sin();
+                }
+
+                //1 in the middle of the island, 0 on the shore and below 0 out at sea
+                let inland: f64 = 1.0 - (dx * dx + dz * dz).sqrt() / shore.max(1.0);
+                let ground: i32 = if inland > 0.0 {
+                    sea_level + (inland * self.peak as f64).round() as i32
+                } else {
+                    sea_level - 1 - ((-inland * radius).round() as i32).min(8)
+                };
+
+                for y in 0..height {
+                    let block: Block = if y > ground {
+                        if y < sea_level {Block::Water} else {Block::Air}
+                    } else if y == ground && ground > sea_level + 1 {
+                        Block::Grass
+                    } else if y == ground || y > ground - 3 && ground <= sea_level + 1 {
+                        Block::Sand
+                    } else if y > ground - 4 {
+                        Block::Dirt
+                    } else {
+                        Block::Stone
+                    };
+                    tile_map.set(x, y, z, block);
+                }
+            }
+        }
+
+        return Ok(tile_map);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+    use crate::generator::ClassicGenerator;
+
+    #[test]
+    fn flat_default_is_stone_dirt_and_grass () {
+        let world: TileMap = FlatGenerator::default().generate_for_size(128, 5).unwrap();
+
+        for (x, y, z, block) in world.positions() {
+            let expected: Block = match y {
+                0..=27 => Block::Stone,
+                28..=30 => Block::Dirt,
+                31 => Block::Grass,
+                _ => Block::Air
+            };
+            assert_eq!(block, expected, "{} {} {}", x, y, z);
+        }
+        assert_eq!(FlatGenerator::default().generate_for_size(128, 6).unwrap(), world);
+    }
+
+    #[test]
+    fn flat_layers_are_cut_off_at_the_top () {
+        let world: TileMap = FlatGenerator::new().layer(Block::Stone, 10).layer(Block::Sand, 100).generate_world(4, 16, 4, 0).unwrap();
+        assert_eq!(world.columns().next().unwrap().blocks().filter(|b| *b == Block::Sand).count(), 6);
+    }
+
+    #[test]
+    fn void_is_all_air () {
+        let world: TileMap = VoidGenerator.generate_for_size(256, 5).unwrap();
+        assert_eq!((world.width(), world.height(), world.depth()), (256, WORLD_HEIGHT, 256));
+        assert!(world.blocks().iter().all(|b| *b == Block::Air));
+    }
+
+    #[test]
+    fn island_is_the_same_for_a_seed_and_surrounded_by_water () {
+        let island: TileMap = IslandGenerator::new().generate_for_size(128, 5).unwrap();
+        assert_eq!(IslandGenerator::new().generate_for_size(128, 5).unwrap(), island);
+        assert_ne!(IslandGenerator::new().generate_for_size(128, 6).unwrap(), island);
+
+        let sea_level: i32 = WORLD_HEIGHT / 2;
+        for column in island.columns().filter(|c| c.x == 0 || c.z == 0 || c.x == 127 || c.z == 127) {
+            assert_eq!(column.get(sea_level - 1), Some(Block::Water), "column {} {}", column.x, column.z);
+            assert_eq!(column.get(sea_level), Some(Block::Air));
+        }
+        assert_eq!(island.get(64, sea_level + 12, 64), Some(Block::Grass));
+    }
+
+    #[test]
+    fn generate_for_size_rejects_invalid_sizes () {
+        let generators: [&dyn WorldGenerator; 4] = [&FlatGenerator::default(), &VoidGenerator, &IslandGenerator::new(), &ClassicGenerator::new(0)];
+        for generator in generators {
+            for size in [0, -128, ClassicGenerator::MAX_SIZE + 1] {
+                assert!(matches!(generator.generate_for_size(size, 0), Err(Error::InvalidDimensions { .. })), "size {}", size);
+            }
+        }
+    }
+}
@@ -0,0 +1,212 @@
+use crate::block::Block;
+use crate::block_pos::BlockPos;
+use crate::diff::ChangePolicy;
+use crate::error::{Error, Result};
+use crate::tile_map::TileMap;
+use crate::generator::ClassicGenerator;
+use crate::world_generator::WorldGenerator;
+use crate::{check_world_size, ChangedBlocks, JSLevel};
+
+use serde::ser::SerializeMap;
+use serde::{Serialize, Serializer};
+
+use std::io::{BufWriter, Write};
+
+/**
+ * Following function writes a level and an edited tile map as a savedGame json
+ * object into any writer. Entries already in the level's changedBlocks are placed
+ * over the tile map, and each block is compared against natural generation and
+ * written straight to the writer as it is reached, so the changed blocks are
+ * never collected in memory even with ChangePolicy::All.
+ * The output matches what the game writes: keys in the order worldSeed,
+ * changedBlocks, worldSize, version followed by any extra keys of the level,
+ * and no whitespace. Changed blocks are ordered by Y, then Z, then X, the order
+ * serialize_saved_game has always written them in, rather than the order the
+ * level's changedBlocks were read in
+ */
+pub fn write_saved_game_json<W: Write> (writer: W, level: &JSLevel, tile_map: &TileMap, policy: ChangePolicy) -> Result<()> {
+    check_world_size(level.worldSize)?;
+    return write_saved_game_json_with(writer, level, tile_map, policy, &ClassicGenerator::new(level.worldSeed));
+}
+
+/**
+ * Same as write_saved_game_json, but blocks are compared against what generator
+ * makes for the level's seed instead of natural generation, for custom maps
+ */
+pub fn write_saved_game_json_with<W: Write> (writer: W, level: &JSLevel, tile_map: &TileMap, policy: ChangePolicy, generator: &dyn WorldGenerator) -> Result<()> {
+
+    let natural: TileMap = generator.generate_for_size(level.worldSize, level.worldSeed)?;
+
+    let expected: (i32, i32, i32) = (natural.width(), natural.height(), natural.depth());
+    let found: (i32, i32, i32) = (tile_map.width(), tile_map.height(), tile_map.depth());
+    if expected != found {
+        return Err(Error::MismatchedDimensions { expected, found });
+    }
+
+    let saved_game: SavedGame = SavedGame {
+        level,
+        changed_blocks: ChangedBlocksStream { level, natural: &natural, edited: tile_map, policy }
+    };
+
+    let mut writer: BufWriter<W> = BufWriter::new(writer);
+    serde_json::to_writer(&mut writer, &saved_game)?;
+    writer.flush()?;
+
+    return Ok(());
+}
+
+//The savedGame object, written field by field so the key order matches the game
+struct SavedGame<'a> {
+    level: &'a JSLevel,
+    changed_blocks: ChangedBlocksStream<'a>
+}
+
+impl Serialize for SavedGame<'_> {
+    fn serialize<S: Serializer> (&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(4 + self.level.extra.len()))?;
+        map.serialize_entry("worldSeed", &self.level.worldSeed)?;
+        map.serialize_entry("changedBlocks", &self.changed_blocks)?;
+        map.serialize_entry("worldSize", &self.level.worldSize)?;
+        map.serialize_entry("version", &self.level.version)?;
+        for (key, value) in &self.level.extra {
+            map.serialize_entry(key, value)?;
+        }
+        return map.end();
+    }
+}
+
+//The changedBlocks object, computed one block at a time while it is written
+struct ChangedBlocksStream<'a> {
+    level: &'a JSLevel,
+    natural: &'a TileMap,
+    edited: &'a TileMap,
+    policy: ChangePolicy
+}
+
+impl Serialize for ChangedBlocksStream<'_> {
+    fn serialize<S: Serializer> (&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let entries = self.edited.positions().zip(self.natural.blocks()).filter_map(|((x, y, z, block), natural)| {
+            let pos: BlockPos = BlockPos::new(x, y, z);
+            let existing: Option<&ChangedBlocks> = self.level.changedBlocks.get(&pos);
+
+            //Existing changed blocks take priority over the tile map
+            let bt: Block = existing.map(|b| b.bt).unwrap_or(block);
+            let differs: bool = bt != *natural;
+
+            if self.policy.includes(differs, existing.is_some()) {
+                Some((pos, ChangedBlocks { a: differs as u8, bt }))
+            } else {
+                None
+            }
+        });
+
+        return serializer.collect_map(entries);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world_generator::FlatGenerator;
+    use crate::{generate_tile_map, ChangedBlockMap};
+
+    /**
+     * serialize_saved_game as it was before the writer, kept as the golden reference.
+     * Its changedBlocks lookup used keys wrapped in quotes and never found an entry,
+     * so it is only compared on levels without changed blocks
+     */
+    fn baseline_serialize (level: &JSLevel, tile_map: &[u8], tile_map1: &[u8], opt: u8) -> String {
+        let x: i32 = level.worldSize;
+        let y: i32 = 64;
+        let z: i32 = level.worldSize;
+
+        let mut output: String = String::from("{");
+        output += &format!(r#""worldSeed":{},"#, level.worldSeed);
+        output += r#""changedBlocks":"#;
+        output += "{";
+
+        let mut flag: bool = false;
+        for i in 0..y {
+            for j in 0..z {
+                for k in 0..x {
+                    let key: String = format!(r#""p{}_{}_{}":"#, k, i, j);
+                    let bt: u8 = 255;
+                    let t: u8 = tile_map[((i * z * x) + (j * x) + k) as usize];
+                    let t1: u8 = tile_map1[((i * z * x) + (j * x) + k) as usize];
+                    let a: u8 = if t == t1 { 0 } else { 1 };
+
+                    if (opt == 2 && a == 1) || (opt == 1 && (bt != 255 || a == 1)) || opt == 0 {
+                        output += &key;
+                        output += "{";
+                        output += &format!(r#""a":{},"bt":{}"#, a, t);
+                        output += "},";
+                        flag = true;
+                    }
+                }
+            }
+        }
+
+        if flag { output.pop(); }
+        output += "},";
+        output += &format!(r#""worldSize":{},"#, level.worldSize);
+        output += &format!(r#""version":{}"#, level.version);
+        output += "}";
+        return output;
+    }
+
+    fn write (level: &JSLevel, tile_map: &TileMap, policy: ChangePolicy, generator: &dyn WorldGenerator) -> String {
+        let mut output: Vec<u8> = Vec::new();
+        write_saved_game_json_with(&mut output, level, tile_map, policy, generator).unwrap();
+        return String::from_utf8(output).unwrap();
+    }
+
+    #[test]
+    fn matches_the_baseline_serializer_byte_for_byte () {
+        let seed: i64 = 20240607;
+        let natural: TileMap = generate_tile_map(128, seed).unwrap();
+
+        //Edits scattered over the world, in no particular order
+        let mut edited: TileMap = natural.clone();
+        for (x, y, z, block) in [(127, 63, 127, Block::Glass), (0, 0, 0, Block::Air), (64, 40, 3, Block::GoldBlock), (3, 40, 64, Block::Tnt), (5, 10, 5, Block::Bricks)] {
+            edited.set(x, y, z, block);
+        }
+
+        let level: JSLevel = JSLevel::new(seed, ChangedBlockMap::new(), 128, 1);
+        let generator: ClassicGenerator = ClassicGenerator::new(seed);
+
+        for (policy, opt) in [(ChangePolicy::Minimal, 2), (ChangePolicy::KeepExisting, 1), (ChangePolicy::All, 0)] {
+            let expected: String = baseline_serialize(&level, &edited.to_flat(), &natural.to_flat(), opt);
+            assert!(write(&level, &edited, policy, &generator) == expected, "{:?} differs from the baseline serializer", policy);
+        }
+    }
+
+    #[test]
+    fn writes_existing_entries_in_tile_map_order () {
+        let generator: FlatGenerator = FlatGenerator::new().layer(Block::Stone, 1);
+        let natural: TileMap = generator.generate_for_size(128, 0).unwrap();
+
+        let mut edited: TileMap = natural.clone();
+        edited.set(2, 0, 1, Block::Dirt);
+
+        //Saved by the game in the order the player placed them
+        let mut changed_blocks: ChangedBlockMap = ChangedBlockMap::new();
+        changed_blocks.insert(BlockPos::new(7, 5, 0), ChangedBlocks::new(1, Block::Glass));
+        changed_blocks.insert(BlockPos::new(1, 0, 0), ChangedBlocks::new(0, Block::Stone));
+        let level: JSLevel = JSLevel::new(9, changed_blocks, 128, 1);
+
+        assert_eq!(write(&level, &edited, ChangePolicy::Minimal, &generator),
+            r#"{"worldSeed":9,"changedBlocks":{"p2_0_1":{"a":1,"bt":3},"p7_5_0":{"a":1,"bt":9}},"worldSize":128,"version":1}"#);
+        assert_eq!(write(&level, &edited, ChangePolicy::KeepExisting, &generator),
+            r#"{"worldSeed":9,"changedBlocks":{"p1_0_0":{"a":0,"bt":2},"p2_0_1":{"a":1,"bt":3},"p7_5_0":{"a":1,"bt":9}},"worldSize":128,"version":1}"#);
+    }
+
+    #[test]
+    fn rejects_a_tile_map_of_another_shape () {
+        let level: JSLevel = JSLevel::new(9, ChangedBlockMap::new(), 128, 1);
+        let mut output: Vec<u8> = Vec::new();
+
+        let result: Result<()> = write_saved_game_json_with(&mut output, &level, &TileMap::new(256, 64, 64), ChangePolicy::Minimal, &FlatGenerator::default());
+        assert!(matches!(result, Err(Error::MismatchedDimensions { expected: (128, 64, 128), found: (256, 64, 64) })));
+        assert!(output.is_empty());
+    }
+}
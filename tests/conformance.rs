@@ -0,0 +1,50 @@
+use mc_classic_js::{generate_tile_map, read_tile_map_dump, Block, ConformanceReport, Error, Fixture, MismatchKind, Stage, TileMap};
+
+#[test]
+fn fixture_round_trips_through_text () {
+    let fixture: Fixture = Fixture { world_size: 128, seed: -5, layer_hashes: vec![0, 1, u64::MAX] };
+    let line: String = fixture.to_string();
+
+    assert_eq!(line, "128 -5 0000000000000000,0000000000000001,ffffffffffffffff");
+    assert_eq!(line.parse::<Fixture>().unwrap(), fixture);
+    assert!("128 -5".parse::<Fixture>().is_err());
+    assert!("128 -5 xyz".parse::<Fixture>().is_err());
+}
+
+#[test]
+fn report_sorts_mismatches_by_kind_and_region () {
+    let expected: TileMap = generate_tile_map(128, 0).unwrap();
+    let mut found: TileMap = expected.clone();
+
+    found.set(0, 1, 0, Block::CoalOre);
+    found.set(100, 1, 100, Block::Log);
+    found.set(100, 2, 100, Block::Lava);
+
+    let report: ConformanceReport = ConformanceReport::compare(&expected, &found).unwrap();
+    assert_eq!(report.mismatches, 3);
+    assert_eq!(report.by_kind.get(&MismatchKind::Ore), Some(&1));
+    assert_eq!(report.by_kind.get(&MismatchKind::Tree), Some(&1));
+    assert_eq!(report.by_kind.get(&MismatchKind::Lava), Some(&1));
+    assert_eq!(report.by_region.get(&(0, 0)), Some(&1));
+    assert_eq!(report.by_region.get(&(3, 3)), Some(&2));
+    assert_eq!(MismatchKind::Tree.stage(), Stage::Planting);
+
+    //Same number of tiles in another shape
+    let error: Error = ConformanceReport::compare(&TileMap::new(128, 64, 256), &TileMap::new(256, 64, 128)).unwrap_err();
+    assert!(matches!(error, Error::MismatchedDimensions { expected: (128, 64, 256), found: (256, 64, 128) }));
+
+    let fixture: Fixture = Fixture::from_tile_map(128, 0, &expected);
+    assert_eq!(fixture.mismatched_layers(&found), vec![1, 2]);
+    assert!(ConformanceReport::check(128, 0, &expected).unwrap().is_match());
+}
+
+#[test]
+fn dumps_read_as_raw_bytes_or_json () {
+    let tile_map: TileMap = generate_tile_map(128, 1).unwrap();
+    let raw: Vec<u8> = tile_map.to_flat();
+    let json: String = serde_json::to_string(&raw).unwrap();
+
+    assert_eq!(read_tile_map_dump(&raw, 128).unwrap(), tile_map);
+    assert_eq!(read_tile_map_dump(json.as_bytes(), 128).unwrap(), tile_map);
+    assert!(read_tile_map_dump(&raw[1..], 128).is_err());
+}
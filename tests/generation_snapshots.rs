@@ -0,0 +1,30 @@
+use mc_classic_js::Fixture;
+
+//Layer hashes of this crate's own output, they catch changes to the generator but say nothing about the js game
+const SNAPSHOTS: &str = include_str!("fixtures/generation_snapshots.txt");
+
+fn check_snapshots (world_size: i32) {
+    let fixtures: Vec<Fixture> = Fixture::parse_all(SNAPSHOTS).unwrap();
+    let fixtures: Vec<&Fixture> = fixtures.iter().filter(|f| f.world_size == world_size).collect();
+    assert!(!fixtures.is_empty(), "no snapshots for size {}", world_size);
+
+    for fixture in fixtures {
+        let layers: Vec<i32> = fixture.check().unwrap();
+        assert!(layers.is_empty(), "size {} seed {} differs on layers {:?}", fixture.world_size, fixture.seed, layers);
+    }
+}
+
+#[test]
+fn small_worlds_match_snapshots () {
+    check_snapshots(128);
+}
+
+#[test]
+fn normal_worlds_match_snapshots () {
+    check_snapshots(256);
+}
+
+#[test]
+fn huge_worlds_match_snapshots () {
+    check_snapshots(512);
+}
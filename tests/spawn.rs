@@ -0,0 +1,48 @@
+use mc_classic_js::{find_spawn, find_spawn_with, generate_tile_map, highest_tile, Block, BlockPos, FlatGenerator, Random, TileMap, VoidGenerator, WorldGenerator, NO_SPAWN_Y};
+
+//Dry land in the middle half of the world, above the water level
+fn assert_valid_spawn (tile_map: &TileMap, spawn: BlockPos) {
+    let (width, depth): (i32, i32) = (tile_map.width(), tile_map.depth());
+    assert!(spawn.x >= width / 4 && spawn.x < width * 3 / 4 && spawn.z >= depth / 4 && spawn.z < depth * 3 / 4, "{} is outside of the middle half", spawn);
+    assert!(spawn.y > tile_map.height() / 2, "{} is below the water level", spawn);
+    assert_eq!(highest_tile(tile_map, spawn.x, spawn.z), spawn.y - 1);
+    assert!(!tile_map.get(spawn.x, spawn.y - 2, spawn.z).unwrap().is_liquid());
+}
+
+#[test]
+fn spawns_are_on_dry_land_for_every_size () {
+    for (size, seed) in [(128, 0), (128, 1), (128, 42), (256, 0), (512, 0)] {
+        let tile_map: TileMap = generate_tile_map(size, seed).unwrap();
+
+        for _ in 0..20 {
+            assert_valid_spawn(&tile_map, find_spawn(&tile_map));
+        }
+        for random_seed in 0..20 {
+            assert_valid_spawn(&tile_map, find_spawn_with(&tile_map, &mut Random::new(random_seed)));
+        }
+    }
+}
+
+#[test]
+fn same_random_gives_the_same_spawn () {
+    let tile_map: TileMap = generate_tile_map(128, 3).unwrap();
+    let spawn: BlockPos = find_spawn_with(&tile_map, &mut Random::new(11));
+
+    assert_eq!(find_spawn_with(&tile_map, &mut Random::new(11)), spawn);
+}
+
+#[test]
+fn spawn_is_on_top_of_flat_worlds () {
+    let tile_map: TileMap = FlatGenerator::new().layer(Block::Stone, 40).generate_for_size(128, 0).unwrap();
+    assert_eq!(find_spawn(&tile_map).y, 41);
+}
+
+#[test]
+fn spawn_without_land_is_below_the_world () {
+    let tile_map: TileMap = VoidGenerator.generate_for_size(128, 0).unwrap();
+    assert_eq!(find_spawn(&tile_map).y, NO_SPAWN_Y);
+
+    let ocean: TileMap = FlatGenerator::new().layer(Block::Sand, 10).layer(Block::Water, 30).generate_for_size(128, 0).unwrap();
+    assert_eq!(highest_tile(&ocean, 5, 5), 10);
+    assert_eq!(find_spawn(&ocean).y, NO_SPAWN_Y);
+}
@@ -0,0 +1,12 @@
+use mc_classic_js::{ClassicGenerator, TileMap};
+
+//Only the noise of the height map stages is threaded, so the world must not change
+#[test]
+fn threads_give_byte_identical_worlds () {
+    for (size, seed) in [(128, 1), (256, 987654321), (512, -42)] {
+        let single: TileMap = ClassicGenerator::new(seed).size(size).threads(1).generate().unwrap();
+        let multi: TileMap = ClassicGenerator::new(seed).size(size).threads(4).generate().unwrap();
+
+        assert!(single.to_flat() == multi.to_flat(), "{} world of seed {} differs between 1 and 4 threads", size, seed);
+    }
+}